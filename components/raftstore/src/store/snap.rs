@@ -5,7 +5,7 @@ use std::{
     error::Error as StdError,
     fmt::{self, Display, Formatter},
     io::{self, ErrorKind, Read, Write},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     result, str,
     sync::{
         atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
@@ -16,7 +16,9 @@ use std::{
 
 use collections::{HashMap, HashMapEntry as Entry};
 use encryption::{create_aes_ctr_crypter, from_engine_encryption_method, DataKeyManager, Iv};
-use engine_traits::{CfName, EncryptionKeyManager, KvEngine, CF_DEFAULT, CF_LOCK, CF_WRITE};
+use engine_traits::{
+    CfName, EncryptionKeyManager, KvEngine, SstCompressionType, CF_DEFAULT, CF_LOCK, CF_WRITE,
+};
 use error_code::{self, ErrorCode, ErrorCodeExt};
 use fail::fail_point;
 use file_system::{
@@ -69,9 +71,21 @@ const SNAP_REV_PREFIX: &str = "rev";
 const DEL_RANGE_PREFIX: &str = "del_range";
 
 const TMP_FILE_SUFFIX: &str = ".tmp";
+// Sibling marker (`gen_a_b_c_d.lock`) present while a tablet snapshot directory
+// is being materialized or torn down. A key with a live lock is treated as
+// nonexistent by `TabletSnapKey::from_path`.
+const LOCK_FILE_SUFFIX: &str = ".lock";
+// Suffix of a single-file tablet snapshot archive (stored, not compressed).
+const ARCHIVE_FILE_SUFFIX: &str = ".tsnap";
 const SST_FILE_SUFFIX: &str = ".sst";
 const CLONE_FILE_SUFFIX: &str = ".clone";
 const META_FILE_SUFFIX: &str = ".meta";
+// Sidecar carrying `SnapshotMetaExt`, the per-snapshot metadata that has no
+// field on kvproto's generated `SnapshotMeta`/`SnapshotCfFile` types.
+const META_EXT_FILE_SUFFIX: &str = ".meta.ext";
+// Per-snapshot content-address manifest shipped alongside a tablet snapshot's
+// CF files so the receiver can re-verify integrity before ingestion.
+const TABLET_SNAP_MANIFEST: &str = "SNAP_MANIFEST";
 
 const DELETE_RETRY_MAX_TIMES: u32 = 6;
 const DELETE_RETRY_TIME_MILLIS: u64 = 500;
@@ -84,6 +98,15 @@ pub enum Error {
     #[error("too many snapshots")]
     TooManySnapshots,
 
+    #[error("snapshot exceeds receive budget: {0}")]
+    SnapshotTooLarge(String),
+
+    #[error("snapshot content hash mismatch: {0}")]
+    ContentHashMismatch(String),
+
+    #[error("unsafe snapshot entry rejected: {0}")]
+    UnsafeSnapshotEntry(String),
+
     #[error("snap failed {0:?}")]
     Other(#[from] Box<dyn StdError + Sync + Send>),
 }
@@ -107,6 +130,9 @@ impl ErrorCodeExt for Error {
         match self {
             Error::Abort => error_code::raftstore::SNAP_ABORT,
             Error::TooManySnapshots => error_code::raftstore::SNAP_TOO_MANY,
+            Error::SnapshotTooLarge(_) => error_code::raftstore::SNAP_TOO_MANY,
+            Error::ContentHashMismatch(_) => error_code::raftstore::SNAP_UNKNOWN,
+            Error::UnsafeSnapshotEntry(_) => error_code::raftstore::SNAP_UNKNOWN,
             Error::Other(_) => error_code::raftstore::SNAP_UNKNOWN,
         }
     }
@@ -149,6 +175,19 @@ impl SnapKey {
         SnapKey::new(region_id, term, index)
     }
 
+    // Parse a `SnapKey` back from its `Display` form (`region_term_idx`), as
+    // stored in an incremental snapshot's meta to reference its base.
+    pub fn parse(s: &str) -> Option<SnapKey> {
+        let parts: Vec<&str> = s.split('_').collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        let region_id = parts[0].parse().ok()?;
+        let term = parts[1].parse().ok()?;
+        let idx = parts[2].parse().ok()?;
+        Some(SnapKey::new(region_id, term, idx))
+    }
+
     pub fn from_snap(snap: &RaftSnapshot) -> io::Result<SnapKey> {
         let mut snap_data = RaftSnapshotData::default();
         if let Err(e) = snap_data.merge_from_bytes(snap.get_data()) {
@@ -191,6 +230,10 @@ where
     pub write_batch_size: usize,
     pub coprocessor_host: CoprocessorHost<EK>,
     pub ingest_copy_symlink: bool,
+    // Number of worker threads used to ingest/apply the per-CF SST files
+    // concurrently. `0`/`1` keeps the apply loop sequential on the calling
+    // thread.
+    pub apply_concurrency: usize,
 }
 
 // A helper function to copy snapshot.
@@ -203,6 +246,25 @@ pub fn copy_snapshot(mut from: Box<Snapshot>, mut to: Box<Snapshot>) -> io::Resu
     Ok(())
 }
 
+// The snapshot-file prefix (`gen_<key>` or `rev_<key>`) under which the base
+// snapshot `key` is materialized in `dir`, or `None` if neither meta file is
+// present. Used both to validate and to locate the base of an incremental
+// snapshot, regardless of whether the base was generated or received here.
+fn base_snapshot_prefix(dir: &Path, key: &SnapKey) -> Option<String> {
+    for snap_prefix in [SNAP_GEN_PREFIX, SNAP_REV_PREFIX] {
+        let prefix = format!("{}_{}", snap_prefix, key);
+        let meta_filename = format!("{}{}", prefix, META_FILE_SUFFIX);
+        if file_exists(&dir.join(meta_filename)) {
+            return Some(prefix);
+        }
+    }
+    None
+}
+
+fn base_snapshot_exists(dir: &Path, key: &SnapKey) -> bool {
+    base_snapshot_prefix(dir, key).is_some()
+}
+
 // Try to delete the specified snapshot, return true if the deletion is done.
 fn retry_delete_snapshot(mgr: &SnapManagerCore, key: &SnapKey, snap: &Snapshot) -> bool {
     let d = time::Duration::from_millis(DELETE_RETRY_TIME_MILLIS);
@@ -215,10 +277,94 @@ fn retry_delete_snapshot(mgr: &SnapManagerCore, key: &SnapKey, snap: &Snapshot)
     false
 }
 
+// Per-`SnapshotCfFile`-entry metadata that has no field on kvproto's
+// generated type: the archive codec used for that file's bytes, the
+// compressed (wire) size/checksum pair, and — for an entry that carries no
+// file of its own — the base-snapshot index it inherits from. One entry per
+// `SnapshotMeta::get_cf_files()` slot, in the same order.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct CfFileMetaExt {
+    pub archive_format: u32,
+    pub wire_size: u64,
+    pub wire_checksum: u32,
+    pub since_index: u64,
+}
+
+// Sidecar metadata for a `SnapshotMeta` that, like `CfFileMetaExt`, has no
+// field on the generated type: the incremental snapshot's base key and the
+// whole-snapshot content digest. kvproto is a vendored external dependency
+// this crate cannot regenerate, so this rides next to the `.meta` file on
+// disk (`META_EXT_FILE_SUFFIX`) instead of being squeezed onto the wire
+// message itself, and travels alongside `SnapshotMeta` through this crate's
+// own send/receive API wherever the two must cross together.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct SnapshotMetaExt {
+    pub cf_files: Vec<CfFileMetaExt>,
+    pub base_snap_key: String,
+    pub content_hash: u64,
+}
+
+impl SnapshotMetaExt {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = format!(
+            "base_snap_key {}\ncontent_hash {}\n",
+            self.base_snap_key, self.content_hash
+        );
+        for cf in &self.cf_files {
+            buf.push_str(&format!(
+                "cf {} {} {} {}\n",
+                cf.archive_format, cf.wire_size, cf.wire_checksum, cf.since_index
+            ));
+        }
+        buf.into_bytes()
+    }
+
+    fn from_bytes(buf: &[u8]) -> RaftStoreResult<Self> {
+        let text =
+            str::from_utf8(buf).map_err(|e| box_err!("corrupt snapshot meta ext: {}", e))?;
+        let mut ext = SnapshotMetaExt::default();
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("base_snap_key") => {
+                    ext.base_snap_key = fields.next().unwrap_or("").to_string();
+                }
+                Some("content_hash") => {
+                    ext.content_hash = fields
+                        .next()
+                        .and_then(|v| v.parse().ok())
+                        .ok_or_else(|| box_err!("invalid snapshot meta ext line `{}`", line))?;
+                }
+                Some("cf") => {
+                    let mut nums = fields.filter_map(|v| v.parse::<u64>().ok());
+                    let mut next_num = || {
+                        nums.next()
+                            .ok_or_else(|| box_err!("invalid snapshot meta ext line `{}`", line))
+                    };
+                    ext.cf_files.push(CfFileMetaExt {
+                        archive_format: next_num()? as u32,
+                        wire_size: next_num()?,
+                        wire_checksum: next_num()? as u32,
+                        since_index: next_num()?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(ext)
+    }
+}
+
 // Create a SnapshotMeta that can be later put into RaftSnapshotData or written
-// into file.
-pub fn gen_snapshot_meta(cf_files: &[CfFile], for_balance: bool) -> RaftStoreResult<SnapshotMeta> {
+// into file, along with the `SnapshotMetaExt` sidecar data that has no field
+// on the generated `SnapshotMeta`/`SnapshotCfFile` types.
+pub fn gen_snapshot_meta(
+    cf_files: &[CfFile],
+    base_key: Option<&SnapKey>,
+    for_balance: bool,
+) -> RaftStoreResult<(SnapshotMeta, SnapshotMetaExt)> {
     let mut meta = Vec::with_capacity(cf_files.len());
+    let mut cf_ext = Vec::with_capacity(cf_files.len());
     for cf_file in cf_files {
         if !SNAPSHOT_CFS.iter().any(|cf| cf_file.cf == *cf) {
             return Err(box_err!(
@@ -234,6 +380,18 @@ pub fn gen_snapshot_meta(cf_files: &[CfFile], for_balance: bool) -> RaftStoreRes
                 cf_file_meta.set_size(*size);
                 cf_file_meta.set_checksum(cf_file.checksum[i]);
                 meta.push(cf_file_meta);
+                // Fall back to the logical pair for snapshots that carry no
+                // separate wire checksum (e.g. uncompressed files).
+                cf_ext.push(CfFileMetaExt {
+                    archive_format: cf_file.archive_format.to_u8() as u32,
+                    wire_size: cf_file.wire_size.get(i).copied().unwrap_or(*size),
+                    wire_checksum: cf_file
+                        .wire_checksum
+                        .get(i)
+                        .copied()
+                        .unwrap_or(cf_file.checksum[i]),
+                    since_index: 0,
+                });
             }
         } else {
             let mut cf_file_meta = SnapshotCfFile::new();
@@ -241,12 +399,65 @@ pub fn gen_snapshot_meta(cf_files: &[CfFile], for_balance: bool) -> RaftStoreRes
             cf_file_meta.set_size(0);
             cf_file_meta.set_checksum(0);
             meta.push(cf_file_meta);
+            // A CF with no files of its own but a non-zero `since_index` is
+            // inherited from the base snapshot rather than being empty.
+            cf_ext.push(CfFileMetaExt {
+                archive_format: 0,
+                wire_size: 0,
+                wire_checksum: 0,
+                since_index: cf_file.since_index,
+            });
         }
     }
     let mut snapshot_meta = SnapshotMeta::default();
     snapshot_meta.set_cf_files(meta.into());
     snapshot_meta.set_for_balance(for_balance);
-    Ok(snapshot_meta)
+    let base_snap_key = base_key.map(|k| k.to_string()).unwrap_or_default();
+    // Stamp the aggregate digest last, once the cf files it folds over are
+    // populated, so the receiver recomputes the same value from the meta it
+    // reads back. `tablet_snap_path` is still empty here; a caller that sets
+    // it afterwards recomputes the digest.
+    let content_hash = snapshot_content_hash(
+        snapshot_meta.get_cf_files(),
+        snapshot_meta.get_tablet_snap_path(),
+    );
+    let meta_ext = SnapshotMetaExt {
+        cf_files: cf_ext,
+        base_snap_key,
+        content_hash,
+    };
+    Ok((snapshot_meta, meta_ext))
+}
+
+/// Fold every `SnapshotCfFile`'s `(cf, size, checksum, file_index)` plus the
+/// snapshot's `tablet_snap_path` into a single order-sensitive digest. The
+/// running hash is mixed with each file's position so that reordering CFs or
+/// files — or swapping in a different meta/tablet path — changes the result,
+/// catching a mismatched or truncated meta before any CF file is opened. A
+/// hash of `0` is treated as "unset" for compatibility with snapshots written
+/// before this field existed.
+fn snapshot_content_hash(cf_files: &[SnapshotCfFile], tablet_snap_path: &str) -> u64 {
+    // 64-bit FNV-1a with a per-position salt folded in.
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET;
+    let mut mix = |value: u64| {
+        hash ^= value;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    };
+    for (idx, cf_file) in cf_files.iter().enumerate() {
+        mix(idx as u64);
+        for byte in cf_file.get_cf().as_bytes() {
+            mix(*byte as u64);
+        }
+        mix(cf_file.get_size());
+        mix(cf_file.get_checksum() as u64);
+    }
+    for byte in tablet_snap_path.as_bytes() {
+        mix(*byte as u64);
+    }
+    // Never collide with the "unset" sentinel.
+    if hash == 0 { FNV_PRIME } else { hash }
 }
 
 fn calc_checksum_and_size(
@@ -264,6 +475,178 @@ fn calc_checksum_and_size(
     Ok((checksum, size))
 }
 
+/// Deterministic work-sharding selector. A snapshot's per-CF (and per-split-
+/// file) operations are distributed across `divisions` workers by index, so
+/// each worker handles the items for which `selects` is true and results can be
+/// reassembled in the original order.
+#[derive(Clone, Copy)]
+pub struct ParallelSelector {
+    pub index: usize,
+    pub divisions: usize,
+}
+
+impl ParallelSelector {
+    pub fn new(index: usize, divisions: usize) -> ParallelSelector {
+        ParallelSelector { index, divisions }
+    }
+
+    #[inline]
+    pub fn selects(&self, i: usize) -> bool {
+        self.divisions <= 1 || i % self.divisions == self.index
+    }
+}
+
+/// Run `f` over every CF file in parallel across `divisions` scoped threads,
+/// round-robin by index (see [`ParallelSelector`]). With `divisions <= 1` the
+/// work runs inline on the calling thread, preserving historical behaviour.
+/// Each worker owns a disjoint subset of the `&mut CfFile`s, so no locking is
+/// needed, and the first error from any worker is returned.
+fn parallel_for_each_cf<F>(
+    divisions: usize,
+    cf_files: &mut [CfFile],
+    f: F,
+) -> RaftStoreResult<()>
+where
+    F: Fn(usize, &mut CfFile) -> RaftStoreResult<()> + Sync,
+{
+    let divisions = divisions.max(1);
+    if divisions == 1 {
+        for (i, cf_file) in cf_files.iter_mut().enumerate() {
+            f(i, cf_file)?;
+        }
+        return Ok(());
+    }
+
+    let mut shards: Vec<Vec<(usize, &mut CfFile)>> =
+        (0..divisions).map(|_| Vec::new()).collect();
+    for (i, cf_file) in cf_files.iter_mut().enumerate() {
+        shards[i % divisions].push((i, cf_file));
+    }
+    let f = &f;
+    let results = thread::scope(|s| {
+        let handles: Vec<_> = shards
+            .into_iter()
+            .map(|shard| {
+                s.spawn(move || -> RaftStoreResult<()> {
+                    for (i, cf_file) in shard {
+                        f(i, cf_file)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    for r in results {
+        r?;
+    }
+    Ok(())
+}
+
+/// Read-only counterpart of [`parallel_for_each_cf`]. Shares immutable `CfFile`
+/// references across `divisions` scoped threads, so it is suited to validation
+/// passes that only read the backing files.
+fn parallel_for_each_cf_ref<F>(
+    divisions: usize,
+    cf_files: &[CfFile],
+    f: F,
+) -> RaftStoreResult<()>
+where
+    F: Fn(usize, &CfFile) -> RaftStoreResult<()> + Sync,
+{
+    let divisions = divisions.max(1);
+    if divisions == 1 {
+        for (i, cf_file) in cf_files.iter().enumerate() {
+            f(i, cf_file)?;
+        }
+        return Ok(());
+    }
+
+    let selectors: Vec<ParallelSelector> = (0..divisions)
+        .map(|index| ParallelSelector::new(index, divisions))
+        .collect();
+    let f = &f;
+    let results = thread::scope(|s| {
+        let handles: Vec<_> = selectors
+            .into_iter()
+            .map(|selector| {
+                s.spawn(move || -> RaftStoreResult<()> {
+                    for (i, cf_file) in cf_files.iter().enumerate() {
+                        if selector.selects(i) {
+                            f(i, cf_file)?;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    for r in results {
+        r?;
+    }
+    Ok(())
+}
+
+/// Like [`parallel_for_each_cf`] but collects a per-CF result, returned in the
+/// original CF order so the caller can assemble `cf_files`/metrics
+/// deterministically after the concurrent build joins.
+fn parallel_map_cf<T, F>(
+    divisions: usize,
+    cf_files: &mut [CfFile],
+    f: F,
+) -> RaftStoreResult<Vec<T>>
+where
+    T: Send,
+    F: Fn(usize, &mut CfFile) -> RaftStoreResult<T> + Sync,
+{
+    let divisions = divisions.max(1);
+    if divisions == 1 {
+        let mut out = Vec::with_capacity(cf_files.len());
+        for (i, cf_file) in cf_files.iter_mut().enumerate() {
+            out.push(f(i, cf_file)?);
+        }
+        return Ok(out);
+    }
+
+    let mut shards: Vec<Vec<(usize, &mut CfFile)>> =
+        (0..divisions).map(|_| Vec::new()).collect();
+    for (i, cf_file) in cf_files.iter_mut().enumerate() {
+        shards[i % divisions].push((i, cf_file));
+    }
+    let f = &f;
+    let results = thread::scope(|s| {
+        let handles: Vec<_> = shards
+            .into_iter()
+            .map(|shard| {
+                s.spawn(move || -> RaftStoreResult<Vec<(usize, T)>> {
+                    let mut out = Vec::with_capacity(shard.len());
+                    for (i, cf_file) in shard {
+                        out.push((i, f(i, cf_file)?));
+                    }
+                    Ok(out)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    let mut indexed: Vec<(usize, T)> = Vec::new();
+    for r in results {
+        indexed.extend(r?);
+    }
+    indexed.sort_by_key(|(i, _)| *i);
+    Ok(indexed.into_iter().map(|(_, t)| t).collect())
+}
+
 fn check_file_size(got_size: u64, expected_size: u64, path: &Path) -> RaftStoreResult<()> {
     if got_size != expected_size {
         return Err(box_err!(
@@ -304,6 +687,84 @@ fn check_file_size_and_checksum(
     Ok(())
 }
 
+/// A thin fault-injection shim around the filesystem operations the snapshot
+/// receive/save/delete paths perform. In production every `guard` call compiles
+/// to `Ok(())`; under `cfg(test)` a per-thread schedule can fail the Nth
+/// operation of a given kind with a chosen [`ErrorKind`], turning otherwise
+/// hard-to-reproduce crash/rename races into deterministic unit tests.
+mod fault {
+    use std::io;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub(super) enum FaultKind {
+        WriteAll,
+        SyncAll,
+        Rename,
+        Delete,
+    }
+
+    #[cfg(test)]
+    mod schedule {
+        use std::{cell::RefCell, io::ErrorKind};
+
+        use super::FaultKind;
+
+        struct Scheduled {
+            kind: FaultKind,
+            remaining: usize,
+            error: ErrorKind,
+        }
+
+        thread_local! {
+            static SCHEDULE: RefCell<Vec<Scheduled>> = const { RefCell::new(Vec::new()) };
+        }
+
+        /// Fail the `nth` (1-based) subsequent operation of `kind` with `error`.
+        pub(in super::super) fn fail_nth(kind: FaultKind, nth: usize, error: ErrorKind) {
+            SCHEDULE.with(|s| {
+                s.borrow_mut().push(Scheduled {
+                    kind,
+                    remaining: nth.max(1),
+                    error,
+                })
+            });
+        }
+
+        /// Drop every scheduled fault on the current thread.
+        pub(in super::super) fn reset() {
+            SCHEDULE.with(|s| s.borrow_mut().clear());
+        }
+
+        pub(super) fn take(kind: FaultKind) -> Option<ErrorKind> {
+            SCHEDULE.with(|s| {
+                let mut s = s.borrow_mut();
+                if let Some(pos) = s.iter().position(|x| x.kind == kind) {
+                    if s[pos].remaining <= 1 {
+                        return Some(s.remove(pos).error);
+                    }
+                    s[pos].remaining -= 1;
+                }
+                None
+            })
+        }
+    }
+
+    #[cfg(test)]
+    pub(in super::super) use schedule::{fail_nth, reset};
+
+    /// Returns an injected error if one is scheduled for `kind`, else `Ok(())`.
+    #[inline]
+    pub(super) fn guard(kind: FaultKind) -> io::Result<()> {
+        #[cfg(test)]
+        if let Some(error) = schedule::take(kind) {
+            return Err(io::Error::new(error, format!("injected fault: {:?}", kind)));
+        }
+        #[cfg(not(test))]
+        let _ = kind;
+        Ok(())
+    }
+}
+
 struct CfFileForRecving {
     file: File,
     encrypter: Option<(Cipher, Crypter)>,
@@ -323,6 +784,22 @@ pub struct CfFile {
     pub kv_count: u64,
     pub size: Vec<u64>,
     pub checksum: Vec<u32>,
+    // Codec actually used to build this CF's SST files, recorded for the apply
+    // side and diagnostics. `None` means no block compression was applied.
+    pub compression: Option<SstCompressionType>,
+    // Archive (whole-file) compression applied to this CF's files before
+    // encryption. Recorded in the snapshot meta so the receiver picks the
+    // matching decoder. `ArchiveFormat::None` keeps the historical raw layout.
+    pub archive_format: snap_io::ArchiveFormat,
+    // Size and crc32 of the compressed-and-encrypted wire bytes, parallel to
+    // `size`/`checksum` (which describe the decrypted stream). Lets the receiver
+    // validate transfer integrity without having to decrypt or decompress.
+    pub wire_size: Vec<u64>,
+    pub wire_checksum: Vec<u32>,
+    // For incremental snapshots: the raft index of the base snapshot this CF is
+    // inherited from. `0` means the CF carries its own data (a full CF). A
+    // non-zero value with empty `size` means "reuse the base snapshot's CF".
+    pub since_index: u64,
 }
 
 impl CfFile {
@@ -389,6 +866,18 @@ impl CfFile {
         self.path.join(file_name).to_str().unwrap().to_string()
     }
 
+    // Record the wire (compressed-and-encrypted) size and checksum for the file
+    // at `idx`, mirroring `add_file_with_size_checksum` for the logical pair.
+    pub fn add_wire_size_checksum(&mut self, idx: usize, size: u64, checksum: u32) {
+        if self.wire_size.len() > idx {
+            self.wire_size[idx] = size;
+            self.wire_checksum[idx] = checksum;
+        } else {
+            self.wire_size.push(size);
+            self.wire_checksum.push(checksum);
+        }
+    }
+
     pub fn gen_file_name(&self, file_id: usize) -> String {
         if file_id == 0 {
             // for backward compatibility
@@ -437,6 +926,11 @@ struct MetaFile {
 
     // for writing snapshot
     pub tmp_path: PathBuf,
+
+    // `SnapshotMetaExt` sidecar, see `META_EXT_FILE_SUFFIX`.
+    pub ext: SnapshotMetaExt,
+    pub ext_path: PathBuf,
+    pub ext_tmp_path: PathBuf,
 }
 
 pub struct Snapshot {
@@ -448,6 +942,10 @@ pub struct Snapshot {
     cf_file_index: usize,
     meta_file: MetaFile,
     hold_tmp_files: bool,
+    // When set, this snapshot is an incremental snapshot layered on top of the
+    // full snapshot identified by `base_key`: CF files whose contents are
+    // unchanged since the base are omitted and inherited from it on apply.
+    base_key: Option<SnapKey>,
 
     mgr: SnapManagerCore,
 }
@@ -495,9 +993,14 @@ impl Snapshot {
         let meta_filename = format!("{}{}", prefix, META_FILE_SUFFIX);
         let meta_path = dir_path.join(&meta_filename);
         let meta_tmp_path = dir_path.join(format!("{}{}", meta_filename, TMP_FILE_SUFFIX));
+        let ext_filename = format!("{}{}", prefix, META_EXT_FILE_SUFFIX);
+        let ext_path = dir_path.join(&ext_filename);
+        let ext_tmp_path = dir_path.join(format!("{}{}", ext_filename, TMP_FILE_SUFFIX));
         let meta_file = MetaFile {
             path: meta_path,
             tmp_path: meta_tmp_path,
+            ext_path,
+            ext_tmp_path,
             ..Default::default()
         };
 
@@ -510,6 +1013,7 @@ impl Snapshot {
             cf_file_index: 0,
             meta_file,
             hold_tmp_files: false,
+            base_key: None,
             mgr: mgr.clone(),
         };
 
@@ -551,6 +1055,31 @@ impl Snapshot {
         Ok(s)
     }
 
+    // Build an incremental snapshot layered on top of the full snapshot
+    // `base_key`. If the referenced base does not exist locally the snapshot
+    // silently falls back to a full snapshot, so callers never have to special
+    // case a missing base.
+    fn new_for_building_incremental<T: Into<PathBuf>>(
+        dir: T,
+        key: &SnapKey,
+        base_key: &SnapKey,
+        mgr: &SnapManagerCore,
+    ) -> RaftStoreResult<Self> {
+        let dir = dir.into();
+        let mut s = Self::new(dir.clone(), key, true, CheckPolicy::ErrAllowed, mgr)?;
+        if base_snapshot_exists(&dir, base_key) {
+            s.base_key = Some(base_key.clone());
+        } else {
+            warn!(
+                "base snapshot missing, falling back to a full snapshot";
+                "snapshot" => %s.path(),
+                "base" => %base_key,
+            );
+        }
+        s.init_for_building()?;
+        Ok(s)
+    }
+
     fn new_for_sending<T: Into<PathBuf>>(
         dir: T,
         key: &SnapKey,
@@ -563,7 +1092,8 @@ impl Snapshot {
             // Skip the initialization below if it doesn't exists.
             return Ok(s);
         }
-        for cf_file in &mut s.cf_files {
+        let divisions = s.mgr.snap_io_concurrency;
+        parallel_for_each_cf(divisions, &mut s.cf_files, |_, cf_file| {
             // initialize cf file size and reader
             let file_paths = cf_file.file_paths();
             for (i, file_path) in file_paths.iter().enumerate() {
@@ -575,7 +1105,8 @@ impl Snapshot {
                         .push(Box::new(file) as Box<dyn Read + Send>);
                 }
             }
-        }
+            Ok(())
+        })?;
         Ok(s)
     }
 
@@ -584,9 +1115,10 @@ impl Snapshot {
         key: &SnapKey,
         mgr: &SnapManagerCore,
         snapshot_meta: SnapshotMeta,
+        meta_ext: SnapshotMetaExt,
     ) -> RaftStoreResult<Self> {
         let mut s = Self::new(dir, key, false, CheckPolicy::ErrNotAllowed, mgr)?;
-        s.set_snapshot_meta(snapshot_meta)?;
+        s.set_snapshot_meta(snapshot_meta, meta_ext)?;
         if s.exists() {
             return Ok(s);
         }
@@ -598,9 +1130,13 @@ impl Snapshot {
         s.meta_file.file = Some(f);
         s.hold_tmp_files = true;
 
-        for cf_file in &mut s.cf_files {
+        // Capture the key manager up front so the parallel closure doesn't
+        // borrow `s` while `s.cf_files` is mutably borrowed below.
+        let key_mgr = s.mgr.encryption_key_manager.clone();
+        let divisions = s.mgr.snap_io_concurrency;
+        parallel_for_each_cf(divisions, &mut s.cf_files, |_, cf_file| {
             if cf_file.size.is_empty() {
-                continue;
+                return Ok(());
             }
             let tmp_file_paths = cf_file.tmp_file_paths();
             let file_paths = cf_file.file_paths();
@@ -620,8 +1156,25 @@ impl Snapshot {
                     write_digest: crc32fast::Hasher::new(),
                 });
 
-                if let Some(mgr) = &s.mgr.encryption_key_manager {
-                    let enc_info = mgr.new_file(&file_paths[idx])?;
+                if let Some(mgr) = &key_mgr {
+                    // A previously-failed receive can leave a key-dictionary
+                    // entry for this file name behind. If the backing file is
+                    // gone the entry is orphaned: drop it and re-register so a
+                    // retry isn't wedged on a spurious collision. A collision
+                    // backed by a real on-disk file stays a hard error.
+                    let enc_info = match mgr.new_file(&file_paths[idx]) {
+                        Ok(info) => info,
+                        Err(e)
+                            if e.kind() == ErrorKind::AlreadyExists
+                                && !Path::new(&file_paths[idx]).exists() =>
+                        {
+                            warn!("overwriting stale encryption key entry for snapshot file";
+                                "file" => &file_paths[idx]);
+                            mgr.delete_file(&file_paths[idx], None)?;
+                            mgr.new_file(&file_paths[idx])?
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
                     let mthd = from_engine_encryption_method(enc_info.method);
                     if mthd != EncryptionMethod::Plaintext {
                         let file_for_recving = cf_file.file_for_recving.last_mut().unwrap();
@@ -637,7 +1190,8 @@ impl Snapshot {
                     }
                 }
             }
-        }
+            Ok(())
+        })?;
         Ok(s)
     }
 
@@ -673,8 +1227,37 @@ impl Snapshot {
         Ok(snapshot_meta)
     }
 
-    // Validate and set SnapshotMeta of this Snapshot.
-    pub fn set_snapshot_meta(&mut self, snapshot_meta: SnapshotMeta) -> RaftStoreResult<()> {
+    fn read_snapshot_meta_ext(&self) -> RaftStoreResult<SnapshotMetaExt> {
+        let buf = file_system::read(&self.meta_file.ext_path)?;
+        SnapshotMetaExt::from_bytes(&buf)
+    }
+
+    // Validate and set SnapshotMeta (plus its `SnapshotMetaExt` sidecar) of
+    // this Snapshot.
+    pub fn set_snapshot_meta(
+        &mut self,
+        snapshot_meta: SnapshotMeta,
+        meta_ext: SnapshotMetaExt,
+    ) -> RaftStoreResult<()> {
+        // Gate on the whole-snapshot digest before touching any CF file, so a
+        // meta that references a reordered or mismatched set of files is
+        // rejected up front rather than mid-ingest. A zero hash means the meta
+        // predates this field, in which case we fall back to per-file crc32.
+        let expected_hash = meta_ext.content_hash;
+        if expected_hash != 0 {
+            let got_hash = snapshot_content_hash(
+                snapshot_meta.get_cf_files(),
+                snapshot_meta.get_tablet_snap_path(),
+            );
+            if got_hash != expected_hash {
+                return Err(box_err!(
+                    "snapshot content hash mismatch, expect {}, got {}",
+                    expected_hash,
+                    got_hash
+                ));
+            }
+        }
+
         let mut cf_file_count_from_meta: Vec<usize> = vec![];
         let mut file_count = 0;
         let mut current_cf = "";
@@ -706,9 +1289,16 @@ impl Snapshot {
                 cf_file_count_from_meta.len()
             ));
         }
+        if meta_ext.cf_files.len() != snapshot_meta.get_cf_files().len() {
+            return Err(box_err!(
+                "snapshot meta ext cf file count mismatch, expect {}, got {}",
+                snapshot_meta.get_cf_files().len(),
+                meta_ext.cf_files.len()
+            ));
+        }
         let mut file_idx = 0;
         let mut cf_idx = 0;
-        for meta in snapshot_meta.get_cf_files() {
+        for (meta, ext) in snapshot_meta.get_cf_files().iter().zip(meta_ext.cf_files.iter()) {
             if cf_idx < cf_file_count_from_meta.len() && file_idx < cf_file_count_from_meta[cf_idx]
             {
                 if meta.get_cf() != self.cf_files[cf_idx].cf {
@@ -725,6 +1315,17 @@ impl Snapshot {
                         meta.get_size(),
                         meta.get_checksum(),
                     );
+                    self.cf_files[cf_idx].archive_format =
+                        snap_io::ArchiveFormat::from_u8(ext.archive_format as u8);
+                    self.cf_files[cf_idx].add_wire_size_checksum(
+                        file_idx,
+                        ext.wire_size,
+                        ext.wire_checksum,
+                    );
+                } else {
+                    // An inherited CF carries no files of its own; remember the
+                    // base index so apply can layer the base snapshot's CF.
+                    self.cf_files[cf_idx].since_index = ext.since_index;
                 }
                 file_idx += 1;
                 if file_idx >= cf_file_count_from_meta[cf_idx] {
@@ -733,13 +1334,33 @@ impl Snapshot {
                 }
             }
         }
+
+        // Resolve and validate the base of an incremental snapshot. If the base
+        // is no longer present locally the snapshot is unusable and the caller
+        // should fall back to requesting a full snapshot.
+        let base = &meta_ext.base_snap_key;
+        if !base.is_empty() {
+            match SnapKey::parse(base) {
+                Some(base_key) if base_snapshot_exists(&self.dir_path, &base_key) => {
+                    self.base_key = Some(base_key);
+                }
+                _ => {
+                    return Err(box_err!(
+                        "incremental snapshot references missing base {}",
+                        base
+                    ));
+                }
+            }
+        }
         self.meta_file.meta = Some(snapshot_meta);
+        self.meta_file.ext = meta_ext;
         Ok(())
     }
 
     fn load_snapshot_meta(&mut self) -> RaftStoreResult<()> {
         let snapshot_meta = self.read_snapshot_meta()?;
-        self.set_snapshot_meta(snapshot_meta)?;
+        let meta_ext = self.read_snapshot_meta_ext()?;
+        self.set_snapshot_meta(snapshot_meta, meta_ext)?;
         // check if there is a data corruption when the meta file exists
         // but cf files are deleted.
         if !self.exists() {
@@ -771,9 +1392,29 @@ impl Snapshot {
 
     fn validate<F>(&self, post_check: F) -> RaftStoreResult<()>
     where
-        F: Fn(&CfFile, usize) -> RaftStoreResult<()>,
+        F: Fn(&CfFile, usize) -> RaftStoreResult<()> + Sync,
     {
-        for cf_file in &self.cf_files {
+        // Gate on the whole-snapshot digest before any per-file work, so a
+        // tampered or swapped meta file is rejected even when the files it
+        // points at are each internally consistent.
+        if let Some(meta) = self.meta_file.meta.as_ref() {
+            let expected_hash = self.meta_file.ext.content_hash;
+            if expected_hash != 0 {
+                let got_hash =
+                    snapshot_content_hash(meta.get_cf_files(), meta.get_tablet_snap_path());
+                if got_hash != expected_hash {
+                    return Err(box_err!(
+                        "snapshot content hash mismatch on validate, expect {}, got {}",
+                        expected_hash,
+                        got_hash
+                    ));
+                }
+            }
+        }
+
+        let key_mgr = self.mgr.encryption_key_manager.as_ref();
+        let post_check = &post_check;
+        parallel_for_each_cf_ref(self.mgr.snap_io_concurrency, &self.cf_files, |_, cf_file| {
             let file_paths = cf_file.file_paths();
             for i in 0..file_paths.len() {
                 if cf_file.size[i] == 0 {
@@ -782,16 +1423,24 @@ impl Snapshot {
                     continue;
                 }
 
-                check_file_size_and_checksum(
-                    Path::new(&file_paths[i]),
-                    cf_file.size[i],
-                    cf_file.checksum[i],
-                    self.mgr.encryption_key_manager.as_ref(),
-                )?;
+                let path = Path::new(&file_paths[i]);
+                if cf_file.archive_format != snap_io::ArchiveFormat::None {
+                    // Archived files validate against the wire checksum so the
+                    // compressed payload never has to be decoded just to check it.
+                    check_file_size(get_file_size(path)?, cf_file.wire_size[i], path)?;
+                    check_file_checksum(calc_crc32(path)?, cf_file.wire_checksum[i], path)?;
+                } else {
+                    check_file_size_and_checksum(
+                        path,
+                        cf_file.size[i],
+                        cf_file.checksum[i],
+                        key_mgr,
+                    )?;
+                }
                 post_check(cf_file, i)?;
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     fn switch_to_cf_file(&mut self, cf: &str) -> io::Result<()> {
@@ -807,7 +1456,20 @@ impl Snapshot {
         }
     }
 
-    // Save `SnapshotMeta` to file.
+    // Write the `SnapshotMetaExt` sidecar next to the `.meta` file.
+    fn save_meta_ext_file(&self) -> RaftStoreResult<()> {
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.meta_file.ext_tmp_path)?;
+        f.write_all(&self.meta_file.ext.to_bytes())?;
+        f.sync_all()?;
+        file_system::rename(&self.meta_file.ext_tmp_path, &self.meta_file.ext_path)?;
+        Ok(())
+    }
+
+    // Save `SnapshotMeta` (and its `SnapshotMetaExt` sidecar) to file.
     // Used in `do_build` and by external crates.
     pub fn save_meta_file(&mut self) -> RaftStoreResult<()> {
         let v = box_try!(self.meta_file.meta.as_ref().unwrap().write_to_bytes());
@@ -817,10 +1479,14 @@ impl Snapshot {
             // None. However in `do_build` it's deleted so we build it again,
             // and then call `save_meta_file` with `meta_file` as None.
             // FIXME: We can fix it later by introducing a better snapshot delete mechanism.
+            box_try!(fault::guard(fault::FaultKind::WriteAll));
             f.write_all(&v[..])?;
             f.flush()?;
+            box_try!(fault::guard(fault::FaultKind::SyncAll));
             f.sync_all()?;
+            box_try!(fault::guard(fault::FaultKind::Rename));
             file_system::rename(&self.meta_file.tmp_path, &self.meta_file.path)?;
+            self.save_meta_ext_file()?;
             self.hold_tmp_files = false;
             Ok(())
         } else {
@@ -866,33 +1532,63 @@ impl Snapshot {
             }
         }
 
+        // For an incremental snapshot, read the base snapshot's per-CF checksums
+        // up front so unchanged CFs can be dropped and inherited on apply.
+        let base_cf_checksums = self.load_base_cf_checksums();
+
         let (begin_key, end_key) = (enc_start_key(region), enc_end_key(region));
-        for (cf_enum, cf) in SNAPSHOT_CFS_ENUM_PAIR {
-            self.switch_to_cf_file(cf)?;
-            let cf_file = &mut self.cf_files[self.cf_index];
+
+        // Build every CF concurrently into its temporary files. Each worker
+        // owns a disjoint subset of `cf_files`; the shared `limiter` throttles
+        // across workers and the per-CF `BuildStatistics` are reassembled in
+        // the original CF order for metrics and the serial post-processing
+        // (rename, incremental dedup) that follows.
+        let divisions = self.mgr.get_max_build_concurrency();
+        let key_mgr = self.mgr.encryption_key_manager.clone();
+        let archive = self.mgr.archive_format;
+        let archive_level = self.mgr.archive_compression_level;
+        let max_per_file_size = self
+            .mgr
+            .get_actual_max_per_file_size(allow_multi_files_snapshot);
+        let build_concurrency = self.mgr.get_build_concurrency();
+        let compression_policy = &self.mgr.compression_policy;
+        let limiter = &self.mgr.limiter;
+        let begin_key_ref = &begin_key;
+        let end_key_ref = &end_key;
+        let cf_stats = parallel_map_cf(divisions, &mut self.cf_files, |_, cf_file| {
             let cf_stat = if plain_file_used(cf_file.cf) {
                 snap_io::build_plain_cf_file::<EK>(
                     cf_file,
-                    self.mgr.encryption_key_manager.as_ref(),
+                    key_mgr.as_ref(),
+                    archive,
+                    archive_level,
                     kv_snap,
-                    &begin_key,
-                    &end_key,
+                    begin_key_ref,
+                    end_key_ref,
                 )?
             } else {
                 snap_io::build_sst_cf_file_list::<EK>(
                     cf_file,
                     engine,
                     kv_snap,
-                    &begin_key,
-                    &end_key,
-                    self.mgr
-                        .get_actual_max_per_file_size(allow_multi_files_snapshot),
-                    &self.mgr.limiter,
-                    self.mgr.encryption_key_manager.clone(),
+                    begin_key_ref,
+                    end_key_ref,
+                    max_per_file_size,
+                    limiter,
+                    key_mgr.clone(),
+                    compression_policy,
+                    build_concurrency,
                 )?
             };
-            SNAPSHOT_LIMIT_GENERATE_BYTES.inc_by(cf_stat.total_size as u64);
             cf_file.kv_count = cf_stat.key_count as u64;
+            Ok((cf_stat.key_count, cf_stat.total_size))
+        })?;
+
+        for (cf_enum, cf) in SNAPSHOT_CFS_ENUM_PAIR {
+            self.switch_to_cf_file(cf)?;
+            let (key_count, total_size) = cf_stats[self.cf_index];
+            SNAPSHOT_LIMIT_GENERATE_BYTES.inc_by(total_size as u64);
+            let cf_file = &mut self.cf_files[self.cf_index];
             if cf_file.kv_count > 0 {
                 // Use `kv_count` instead of file size to check empty files because encrypted
                 // sst files contain some metadata so their sizes will never be 0.
@@ -909,29 +1605,93 @@ impl Snapshot {
                 }
             }
 
-            SNAPSHOT_CF_KV_COUNT
-                .get(*cf_enum)
-                .observe(cf_stat.key_count as f64);
-            SNAPSHOT_CF_SIZE
-                .get(*cf_enum)
-                .observe(cf_stat.total_size as f64);
+            // Incremental dedup: if this CF is byte-for-byte identical to the
+            // base snapshot's CF, drop the freshly built files and mark the CF
+            // as inherited so only the delta CFs are shipped.
+            if let (Some(base_key), Some(base_sums)) =
+                (self.base_key.clone(), base_cf_checksums.as_ref())
+            {
+                let unchanged = {
+                    let cf_file = &self.cf_files[self.cf_index];
+                    cf_file.kv_count > 0
+                        && base_sums.get(cf).map_or(false, |s| *s == cf_file.checksum)
+                };
+                if unchanged {
+                    self.inherit_cf_from_base(self.cf_index, base_key.idx)?;
+                }
+            }
+
+            SNAPSHOT_CF_KV_COUNT.get(*cf_enum).observe(key_count as f64);
+            SNAPSHOT_CF_SIZE.get(*cf_enum).observe(total_size as f64);
             info!(
                 "scan snapshot of one cf";
                 "region_id" => region.get_id(),
                 "snapshot" => self.path(),
                 "cf" => cf,
-                "key_count" => cf_stat.key_count,
-                "size" => cf_stat.total_size,
+                "key_count" => key_count,
+                "size" => total_size,
             );
         }
 
         // save snapshot meta to meta file
-        self.meta_file.meta = Some(gen_snapshot_meta(&self.cf_files[..], for_balance)?);
+        let (meta, meta_ext) =
+            gen_snapshot_meta(&self.cf_files[..], self.base_key.as_ref(), for_balance)?;
+        self.meta_file.meta = Some(meta);
+        self.meta_file.ext = meta_ext;
         self.save_meta_file()?;
         Ok(())
     }
 
+    // Read the per-CF checksums of this snapshot's base, keyed by CF name, so
+    // `do_build` can detect CFs that are unchanged since the base. Returns
+    // `None` for a full snapshot or when the base meta can't be read.
+    fn load_base_cf_checksums(&self) -> Option<HashMap<String, Vec<u32>>> {
+        let base_key = self.base_key.as_ref()?;
+        let prefix = base_snapshot_prefix(&self.dir_path, base_key)?;
+        let meta_path = self
+            .dir_path
+            .join(format!("{}{}", prefix, META_FILE_SUFFIX));
+        let buf = file_system::read(&meta_path).ok()?;
+        let mut meta = SnapshotMeta::default();
+        meta.merge_from_bytes(&buf).ok()?;
+        let mut map: HashMap<String, Vec<u32>> = HashMap::new();
+        for cf in meta.get_cf_files() {
+            if cf.get_size() != 0 {
+                map.entry(cf.get_cf().to_string())
+                    .or_default()
+                    .push(cf.get_checksum());
+            }
+        }
+        Some(map)
+    }
+
+    // Drop the freshly built files of the CF at `cf_index` and mark it as
+    // inherited from the base snapshot at `base_idx`.
+    fn inherit_cf_from_base(&mut self, cf_index: usize, base_idx: u64) -> RaftStoreResult<()> {
+        let key_mgr = self.mgr.encryption_key_manager.clone();
+        let cf_file = &mut self.cf_files[cf_index];
+        for p in cf_file.file_paths() {
+            delete_file_if_exist(Path::new(&p))?;
+            if let Some(ref mgr) = key_mgr {
+                mgr.delete_file(&p, None)?;
+            }
+        }
+        cf_file.size.clear();
+        cf_file.checksum.clear();
+        cf_file.file_names.clear();
+        cf_file.wire_size.clear();
+        cf_file.wire_checksum.clear();
+        cf_file.kv_count = 0;
+        cf_file.since_index = base_idx;
+        Ok(())
+    }
+
     fn delete(&self) {
+        // Test hook: simulate a delete that fails to make progress so callers
+        // can assert cleanup is retried/idempotent.
+        if fault::guard(fault::FaultKind::Delete).is_err() {
+            return;
+        }
         macro_rules! try_delete_snapshot_files {
             ($cf_file:ident, $file_name_func:ident) => {
                 let mut file_id = 0;
@@ -1013,8 +1773,10 @@ impl Snapshot {
             }
         }
         delete_file_if_exist(&self.meta_file.path).unwrap();
+        delete_file_if_exist(&self.meta_file.ext_path).unwrap();
         if self.hold_tmp_files {
             delete_file_if_exist(&self.meta_file.tmp_path).unwrap();
+            delete_file_if_exist(&self.meta_file.ext_tmp_path).unwrap();
         }
     }
 
@@ -1028,9 +1790,12 @@ impl Snapshot {
     ) -> RaftStoreResult<Self> {
         let mut s = Self::new(dir, key, false, CheckPolicy::ErrNotAllowed, mgr)?;
         s.init_for_building()?;
-        let mut meta = gen_snapshot_meta(&s.cf_files[..], for_balance)?;
+        let (mut meta, mut meta_ext) = gen_snapshot_meta(&s.cf_files[..], None, for_balance)?;
         meta.tablet_snap_path = tablet_snapshot_path.to_string();
+        meta_ext.content_hash =
+            snapshot_content_hash(meta.get_cf_files(), meta.get_tablet_snap_path());
         s.meta_file.meta = Some(meta);
+        s.meta_file.ext = meta_ext;
         s.save_meta_file()?;
         Ok(s)
     }
@@ -1043,6 +1808,10 @@ impl Snapshot {
     pub fn snapshot_meta(&self) -> &Option<SnapshotMeta> {
         &self.meta_file.meta
     }
+
+    pub fn snapshot_meta_ext(&self) -> &SnapshotMetaExt {
+        &self.meta_file.ext
+    }
 }
 
 impl fmt::Debug for Snapshot {
@@ -1101,7 +1870,58 @@ impl Snapshot {
         Ok(snap_data)
     }
 
+    // For an incremental snapshot, repoint every inherited CF at the base
+    // snapshot's files so the regular apply loop ingests the base CF first and
+    // the delta CFs afterwards, producing the same result as a full snapshot.
+    fn hydrate_inherited_cfs_from_base(&mut self) -> RaftStoreResult<()> {
+        let base_key = match self.base_key.clone() {
+            Some(k) => k,
+            None => return Ok(()),
+        };
+        let prefix = match base_snapshot_prefix(&self.dir_path, &base_key) {
+            Some(p) => p,
+            None => {
+                return Err(box_err!(
+                    "incremental snapshot references missing base {}",
+                    base_key
+                ));
+            }
+        };
+        let buf = file_system::read(self.dir_path.join(format!("{}{}", prefix, META_FILE_SUFFIX)))?;
+        let mut base_meta = SnapshotMeta::default();
+        base_meta.merge_from_bytes(&buf)?;
+        let ext_buf = file_system::read(
+            self.dir_path
+                .join(format!("{}{}", prefix, META_EXT_FILE_SUFFIX)),
+        )?;
+        let base_ext = SnapshotMetaExt::from_bytes(&ext_buf)?;
+        for cf_file in &mut self.cf_files {
+            if !cf_file.size.is_empty() || cf_file.since_index == 0 {
+                continue;
+            }
+            // Rebind this CF to the base snapshot's files and restore its size/
+            // checksum accounting from the base meta.
+            cf_file.file_prefix = format!("{}_{}", prefix, cf_file.cf);
+            let mut file_idx = 0;
+            for (meta, ext) in base_meta.get_cf_files().iter().zip(base_ext.cf_files.iter()) {
+                if meta.get_cf() != cf_file.cf || meta.get_size() == 0 {
+                    continue;
+                }
+                cf_file.add_file_with_size_checksum(file_idx, meta.get_size(), meta.get_checksum());
+                cf_file.archive_format = snap_io::ArchiveFormat::from_u8(ext.archive_format as u8);
+                cf_file.add_wire_size_checksum(
+                    file_idx,
+                    ext.wire_size,
+                    ext.wire_checksum,
+                );
+                file_idx += 1;
+            }
+        }
+        Ok(())
+    }
+
     pub fn apply<EK: KvEngine>(&mut self, options: ApplyOptions<EK>) -> Result<()> {
+        box_try!(self.hydrate_inherited_cfs_from_base());
         let apply_without_ingest = self
             .mgr
             .can_apply_cf_without_ingest(self.total_size(), self.total_count());
@@ -1114,12 +1934,14 @@ impl Snapshot {
                         &file_paths[offset],
                         &clone_file_paths[offset],
                         self.mgr.encryption_key_manager.as_deref(),
+                        self.mgr.verify_apply_fs_security,
                     )?;
                 } else {
                     sst_importer::prepare_sst_for_ingestion(
                         &file_paths[offset],
                         &clone_file_paths[offset],
                         self.mgr.encryption_key_manager.as_deref(),
+                        self.mgr.verify_apply_fs_security,
                     )?;
                 }
             }
@@ -1133,11 +1955,10 @@ impl Snapshot {
         let region = options.region;
         let key_mgr = self.mgr.encryption_key_manager.clone();
         let batch_size = options.write_batch_size;
-        for cf_file in &mut self.cf_files {
-            if cf_file.size.is_empty() {
-                // Skip empty cf file.
-                continue;
-            }
+
+        // Apply a single CF file, either streaming plain KVs or ingesting its
+        // SST files. Shared by both the sequential and parallel paths.
+        let apply_cf_file = |cf_file: &CfFile| -> Result<()> {
             let cf = cf_file.cf;
             let mut cb = |kv: &[(Vec<u8>, Vec<u8>)]| {
                 coprocessor_host.post_apply_plain_kvs_from_snapshot(&region, cf, kv)
@@ -1147,6 +1968,7 @@ impl Snapshot {
                 snap_io::apply_plain_cf_file(
                     path,
                     key_mgr.as_ref(),
+                    cf_file.archive_format,
                     &abort_checker,
                     &options.db,
                     cf,
@@ -1180,10 +2002,62 @@ impl Snapshot {
                         cf,
                         enc_start_key(&region),
                         enc_end_key(&region),
+                        // Keep the SST-encoded versions by default; callers that
+                        // need a uniform version pass it explicitly.
+                        None,
                     )?;
                     coprocessor_host.post_apply_sst_from_snapshot(&region, cf, path);
                 }
             }
+            Ok(())
+        };
+
+        let non_empty: Vec<&CfFile> = self
+            .cf_files
+            .iter()
+            .filter(|cf_file| !cf_file.size.is_empty())
+            .collect();
+        let divisions = options.apply_concurrency.max(1);
+        if divisions == 1 {
+            for cf_file in non_empty {
+                check_abort(&abort_checker.0)?;
+                apply_cf_file(cf_file)?;
+            }
+            return Ok(());
+        }
+
+        // Fan the per-CF ingest across `divisions` workers; each worker claims a
+        // deterministic subset of files via `ParallelSelector` and checks the
+        // shared abort flag cooperatively so a cancelled apply stops promptly.
+        let apply_cf_file = &apply_cf_file;
+        let non_empty = &non_empty;
+        let abort = &abort_checker.0;
+        let selectors: Vec<ParallelSelector> = (0..divisions)
+            .map(|index| ParallelSelector::new(index, divisions))
+            .collect();
+        let results = thread::scope(|s| {
+            let handles: Vec<_> = selectors
+                .into_iter()
+                .map(|selector| {
+                    s.spawn(move || -> Result<()> {
+                        for (i, cf_file) in non_empty.iter().enumerate() {
+                            if !selector.selects(i) {
+                                continue;
+                            }
+                            check_abort(abort)?;
+                            apply_cf_file(cf_file)?;
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        for r in results {
+            r?;
         }
         Ok(())
     }
@@ -1192,6 +2066,59 @@ impl Snapshot {
         &self.display_path
     }
 
+    /// The aggregate content digest declared in this snapshot's meta, or `0` if
+    /// the meta predates the field. Exposed so the transport/apply layer can log
+    /// or compare it end to end.
+    pub fn content_hash(&self) -> u64 {
+        self.meta_file.ext.content_hash
+    }
+
+    /// Recompute the aggregate content digest from the on-disk CF files and
+    /// compare it against the value declared in the meta, catching silent
+    /// bit-rot or a truncated receive that the per-file checks performed
+    /// elsewhere might miss. A declared hash of `0` (meta predating the field)
+    /// is treated as "not verifiable" and accepted.
+    pub fn verify_content_hash(&self) -> RaftStoreResult<()> {
+        let expected = self.content_hash();
+        if expected == 0 {
+            return Ok(());
+        }
+        let key_manager = self.mgr.encryption_key_manager.as_ref();
+        let mut recomputed = Vec::with_capacity(self.cf_files.len());
+        for cf_file in &self.cf_files {
+            if cf_file.size.is_empty() {
+                let mut m = SnapshotCfFile::new();
+                m.set_cf(cf_file.cf.to_string());
+                recomputed.push(m);
+                continue;
+            }
+            for (i, file_path) in cf_file.file_paths().iter().enumerate() {
+                let (checksum, size) = if cf_file.size[i] == 0 {
+                    (0, 0)
+                } else {
+                    calc_checksum_and_size(Path::new(file_path), key_manager)?
+                };
+                let mut m = SnapshotCfFile::new();
+                m.set_cf(cf_file.cf.to_string());
+                m.set_size(size);
+                m.set_checksum(checksum);
+                recomputed.push(m);
+            }
+        }
+        let tablet_snap_path = self
+            .meta_file
+            .meta
+            .as_ref()
+            .map_or("", |m| m.get_tablet_snap_path());
+        let got = snapshot_content_hash(&recomputed, tablet_snap_path);
+        if got != expected {
+            return Err(RaftStoreError::Snapshot(Error::ContentHashMismatch(
+                format!("expected {:x}, recomputed {:x}", expected, got),
+            )));
+        }
+        Ok(())
+    }
+
     pub fn exists(&self) -> bool {
         self.cf_files.iter().all(|cf_file| {
             cf_file.size.is_empty()
@@ -1211,12 +2138,22 @@ impl Snapshot {
     }
 
     pub fn total_size(&self) -> u64 {
+        // Report the on-wire/on-disk footprint: when a CF's files were archive
+        // compressed, `wire_size` tracks the compressed-and-encrypted bytes that
+        // actually land on disk, so `max_total_size` accounting reflects real
+        // usage. Uncompressed CFs carry no `wire_size` and fall back to `size`.
         self.cf_files
             .iter()
-            .map(|cf| cf.size.iter().sum::<u64>())
-            .sum()
-    }
-
+            .map(|cf| {
+                if cf.wire_size.is_empty() {
+                    cf.size.iter().sum::<u64>()
+                } else {
+                    cf.wire_size.iter().sum::<u64>()
+                }
+            })
+            .sum()
+    }
+
     pub fn total_count(&self) -> u64 {
         self.cf_files.iter().map(|cf| cf.kv_count).sum()
     }
@@ -1235,6 +2172,7 @@ impl Snapshot {
             // Check each cf file has been fully written, and the checksum matches.
             for (i, mut file_for_recving) in cf_file.file_for_recving.drain(..).enumerate() {
                 file_for_recving.file.flush()?;
+                fault::guard(fault::FaultKind::SyncAll)?;
                 file_for_recving.file.sync_all()?;
 
                 if file_for_recving.written_size != cf_file.size[i] {
@@ -1271,6 +2209,7 @@ impl Snapshot {
             let tmp_paths = cf_file.tmp_file_paths();
             let paths = cf_file.file_paths();
             for (i, tmp_path) in tmp_paths.iter().enumerate() {
+                fault::guard(fault::FaultKind::Rename)?;
                 file_system::rename(tmp_path, &paths[i])?;
             }
         }
@@ -1280,10 +2219,26 @@ impl Snapshot {
         let v = self.meta_file.meta.as_ref().unwrap().write_to_bytes()?;
         {
             let mut meta_file = self.meta_file.file.take().unwrap();
+            fault::guard(fault::FaultKind::WriteAll)?;
             meta_file.write_all(&v[..])?;
+            fault::guard(fault::FaultKind::SyncAll)?;
             meta_file.sync_all()?;
         }
+        fault::guard(fault::FaultKind::Rename)?;
         file_system::rename(&self.meta_file.tmp_path, &self.meta_file.path)?;
+
+        // write the SnapshotMetaExt sidecar
+        {
+            let mut ext_file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&self.meta_file.ext_tmp_path)?;
+            ext_file.write_all(&self.meta_file.ext.to_bytes())?;
+            ext_file.sync_all()?;
+        }
+        file_system::rename(&self.meta_file.ext_tmp_path, &self.meta_file.ext_path)?;
+
         sync_dir(&self.dir_path)?;
         self.hold_tmp_files = false;
         Ok(())
@@ -1350,6 +2305,21 @@ impl Write for Snapshot {
             }
 
             assert!(cf_file.size[self.cf_file_index] != 0);
+            // In apply-while-receiving mode each segment is checksum-verified
+            // the moment its declared `size` is reached, so a finished segment
+            // can be handed to ingest without waiting for the whole snapshot to
+            // land. Archived CFs are verified against the wire checksum (the
+            // bytes actually on the wire); others against the logical checksum.
+            let streaming_apply = self.mgr.enable_streaming_apply;
+            let segment_expected_checksum = if streaming_apply {
+                if cf_file.archive_format != snap_io::ArchiveFormat::None {
+                    cf_file.wire_checksum.get(self.cf_file_index).copied()
+                } else {
+                    cf_file.checksum.get(self.cf_file_index).copied()
+                }
+            } else {
+                None
+            };
             let mut file_for_recving = cf_file
                 .file_for_recving
                 .get_mut(self.cf_file_index)
@@ -1395,6 +2365,22 @@ impl Write for Snapshot {
                 start += acquire;
             }
             if switch {
+                // Segment complete: gate on its checksum before it becomes
+                // eligible for streaming ingest. Detecting a mismatch here
+                // fails the transfer early rather than mid-ingest, and `Drop`
+                // still cleans up the partially-received tmp files.
+                if let Some(expected) = segment_expected_checksum {
+                    let got = file_for_recving.write_digest.clone().finalize();
+                    if got != expected {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "snapshot segment checksum mismatch, expect {}, got {}",
+                                expected, got
+                            ),
+                        ));
+                    }
+                }
                 next_buf = &next_buf[write_len..];
                 self.cf_file_index += 1;
                 if self.cf_file_index >= cf_file.size.len() {
@@ -1441,6 +2427,10 @@ pub struct SnapStats {
     pub sending_count: usize,
     pub receiving_count: usize,
     pub stats: Vec<SnapshotStat>,
+    // Lifetime counters distinguishing full snapshot builds from incremental
+    // (delta) ones layered on a base snapshot.
+    pub full_sends: u64,
+    pub incremental_sends: u64,
 }
 
 #[derive(Clone)]
@@ -1454,6 +2444,63 @@ struct SnapManagerCore {
     encryption_key_manager: Option<Arc<DataKeyManager>>,
     max_per_file_size: Arc<AtomicU64>,
     enable_multi_snapshot_files: Arc<AtomicBool>,
+    // Number of worker threads used to build each SST-format CF file. `1`
+    // retains the historical single-threaded scan.
+    build_concurrency: Arc<AtomicUsize>,
+    // Upper bound on how many CFs `do_build` builds concurrently. `1` keeps the
+    // historical CF-at-a-time behaviour.
+    max_build_concurrency: usize,
+    // Per-CF SST compression policy used when building snapshot files.
+    compression_policy: snap_io::SstCompressionPolicy,
+    // Whole-file archive compression applied to plain-format CF files before
+    // encryption. Defaults to `None`, preserving the historical wire format.
+    archive_format: snap_io::ArchiveFormat,
+    // Compression effort for `archive_format`. `0` asks each codec for its own
+    // default level; higher values trade CPU for a smaller wire footprint.
+    archive_compression_level: i32,
+    // When set, each received CF segment is checksum-verified as soon as its
+    // declared size is reached (apply-while-receiving), gating ingest per
+    // segment instead of after the whole snapshot lands.
+    enable_streaming_apply: bool,
+    // Refcount of incremental snapshots that declare a given snapshot as their
+    // base. A base with a non-zero count must not be GC'd while a dependent
+    // incremental might still need it.
+    base_dependents: Arc<RwLock<HashMap<SnapKey, usize>>>,
+    // Lifetime counters for full vs incremental snapshot builds.
+    full_sends: Arc<AtomicU64>,
+    incremental_sends: Arc<AtomicU64>,
+    // Number of scoped worker threads used to open/decrypt/checksum CF files in
+    // `new_for_sending`/`new_for_receiving`/`validate`. `1` keeps those loops
+    // sequential on the calling thread.
+    snap_io_concurrency: usize,
+    // Retention policy for the snapshot directory. `max_snapshots_per_region`
+    // bounds how many idle snapshots are kept per region (newest by
+    // `term`/`idx` win); `0` disables the per-region cap. `max_total_size`
+    // already bounds the aggregate footprint during building, and the periodic
+    // `sweep_expired_snapshots` sweep enforces this per-region cap too.
+    max_snapshots_per_region: usize,
+    // Count-based cap on idle snapshots kept in the directory regardless of
+    // total-size pressure. The newest `max_snapshots_to_retain` idle snapshots
+    // (by file modified time) are kept per sending/receiving role and older
+    // ones are dropped by `gc_idle_snapshots`; `0` disables the cap.
+    max_snapshots_to_retain: usize,
+    // Hardened-unpack budget for received snapshots. `max_recv_snap_size` caps
+    // the cumulative declared/written bytes across all CF files and
+    // `max_recv_file_count` caps the number of CF files; either `0` disables the
+    // corresponding check. They guard against a hostile or buggy sender
+    // exhausting the receiver's disk before `max_total_size` is ever consulted.
+    max_recv_snap_size: u64,
+    max_recv_file_count: usize,
+    // When set, `get_snapshot_for_applying` recomputes the aggregate content
+    // digest from the on-disk CF files and rejects the snapshot if it diverges
+    // from the value stored in the meta. Disabled by default to keep the apply
+    // path cheap for performance-sensitive deployments.
+    verify_apply_content_hash: bool,
+    // Filesystem security audit of ingestion clone paths on the apply path (see
+    // `sst_importer::verify_fs_security`). `None` disables it; `Some(downgrade)`
+    // enables it, only warning instead of failing the apply when `downgrade` is
+    // set.
+    verify_apply_fs_security: Option<bool>,
     stats: Arc<Mutex<Vec<SnapshotStat>>>,
     // Minimal column family size & kv counts for applying by ingest.
     min_ingest_cf_size: u64,
@@ -1629,9 +2676,139 @@ impl SnapManager {
 
         let base = &self.core.base;
         let f = Snapshot::new_for_building(base, key, &self.core)?;
+        self.core.full_sends.fetch_add(1, Ordering::SeqCst);
+        Ok(Box::new(f))
+    }
+
+    /// Like [`get_snapshot_for_building`] but produces an incremental snapshot
+    /// layered on top of `base_key`. CFs unchanged since the base are omitted
+    /// and inherited on apply; if the base is missing locally the snapshot
+    /// transparently falls back to a full one.
+    pub fn get_snapshot_for_building_incremental(
+        &self,
+        key: &SnapKey,
+        base_key: &SnapKey,
+    ) -> RaftStoreResult<Box<Snapshot>> {
+        let base = &self.core.base;
+        let f = Snapshot::new_for_building_incremental(base, key, base_key, &self.core)?;
+        if f.base_key.is_some() {
+            // Pin the base so GC won't drop it while this incremental still
+            // needs it. The dependency is released when the base is no longer
+            // referenced.
+            self.core.register_base_dependency(base_key);
+            self.core.incremental_sends.fetch_add(1, Ordering::SeqCst);
+        } else {
+            // The base was missing, so this is a full snapshot.
+            self.core.full_sends.fetch_add(1, Ordering::SeqCst);
+        }
         Ok(Box::new(f))
     }
 
+    /// Release a previously registered incremental dependency on `base_key`,
+    /// e.g. once the dependent incremental snapshot has been applied or
+    /// discarded, so the base becomes eligible for GC again.
+    pub fn release_base_dependency(&self, base_key: &SnapKey) {
+        self.core.release_base_dependency(base_key);
+    }
+
+    /// Enforce the configured retention policy over idle snapshots. For each
+    /// region the newest `max_snapshots_per_region` snapshots (ordered by
+    /// `term`/`idx`) are kept and any older ones are deleted. In-flight
+    /// snapshots are never returned by [`list_idle_snap`], so a transfer is
+    /// never interrupted. Intended to be invoked from the periodic GC sweep;
+    /// a limit of `0` disables the cap and makes this a no-op.
+    pub fn sweep_expired_snapshots(&self) {
+        let limit = self.core.max_snapshots_per_region;
+        if limit == 0 {
+            return;
+        }
+        let idle = match self.list_idle_snap() {
+            Ok(v) => v,
+            Err(e) => {
+                error!("failed to list idle snapshots for retention sweep"; "err" => ?e);
+                return;
+            }
+        };
+        // Group idle snapshots by region, remembering whether each was a
+        // sending (gen) or receiving (rev) snapshot so it can be reopened for
+        // deletion.
+        let mut per_region: HashMap<u64, Vec<(SnapKey, bool)>> = HashMap::default();
+        for (key, is_sending) in idle {
+            per_region
+                .entry(key.region_id)
+                .or_default()
+                .push((key, is_sending));
+        }
+        for snaps in per_region.values_mut() {
+            if snaps.len() <= limit {
+                continue;
+            }
+            // Newest first by (term, idx); keep the first `limit`, evict the rest.
+            snaps.sort_by_key(|(key, _)| Reverse((key.term, key.idx)));
+            for (key, is_sending) in snaps.iter().skip(limit) {
+                match self.get_snapshot_for_gc(key, *is_sending) {
+                    Ok(snap) => {
+                        self.delete_snapshot(key, snap.as_ref(), true);
+                    }
+                    Err(e) => {
+                        error!("failed to open snapshot for retention eviction";
+                            "snap_key" => %key, "err" => ?e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Enforce the count-based retention cap over idle snapshots, independent of
+    /// total-size pressure. Idle snapshots are grouped by sending/receiving
+    /// role, sorted by file `modified()` time (newest first), and everything
+    /// beyond `max_snapshots_to_retain` is deleted. Keys currently present in
+    /// the registry are never returned by [`list_idle_snap`], so in-flight
+    /// transfers are never interrupted. A retention count of `0` disables the cap.
+    pub fn gc_idle_snapshots(&self) {
+        let limit = self.core.max_snapshots_to_retain;
+        if limit == 0 {
+            return;
+        }
+        let idle = match self.list_idle_snap() {
+            Ok(v) => v,
+            Err(e) => {
+                error!("failed to list idle snapshots for gc"; "err" => ?e);
+                return;
+            }
+        };
+        // Partition by role, keeping each snapshot's modified time for ordering.
+        let mut by_role: HashMap<bool, Vec<(SnapKey, std::time::SystemTime)>> = HashMap::default();
+        for (key, is_sending) in idle {
+            let snap = match self.get_snapshot_for_gc(&key, is_sending) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let modified = match snap.meta().and_then(|m| m.modified()) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            by_role.entry(is_sending).or_default().push((key, modified));
+        }
+        for (is_sending, mut snaps) in by_role {
+            if snaps.len() <= limit {
+                continue;
+            }
+            snaps.sort_by_key(|(_, modified)| Reverse(*modified));
+            for (key, _) in snaps.iter().skip(limit) {
+                match self.get_snapshot_for_gc(key, is_sending) {
+                    Ok(snap) => {
+                        self.delete_snapshot(key, snap.as_ref(), true);
+                    }
+                    Err(e) => {
+                        error!("failed to open snapshot for gc eviction";
+                            "snap_key" => %key, "err" => ?e);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn get_snapshot_for_gc(
         &self,
         key: &SnapKey,
@@ -1656,16 +2833,24 @@ impl SnapManager {
             Some(m) => m,
             None => return Ok(Box::new(s)),
         };
-        for cf_file in &mut s.cf_files {
-            let file_paths = cf_file.file_paths();
-            for (i, file_path) in file_paths.iter().enumerate() {
-                if cf_file.size[i] == 0 {
-                    continue;
+        // Set up the per-file decrypter readers across the scoped I/O pool.
+        // File order inside each CF is preserved because every worker writes
+        // only its own `file_for_sending[i]` slots.
+        parallel_for_each_cf(
+            self.core.snap_io_concurrency,
+            &mut s.cf_files,
+            |_, cf_file| {
+                let file_paths = cf_file.file_paths();
+                for (i, file_path) in file_paths.iter().enumerate() {
+                    if cf_file.size[i] == 0 {
+                        continue;
+                    }
+                    let reader = snap_io::get_decrypter_reader(file_path, key_manager)?;
+                    cf_file.file_for_sending[i] = reader;
                 }
-                let reader = snap_io::get_decrypter_reader(file_path, key_manager)?;
-                cf_file.file_for_sending[i] = reader;
-            }
-        }
+                Ok(())
+            },
+        )?;
         Ok(Box::new(s))
     }
 
@@ -1676,10 +2861,12 @@ impl SnapManager {
         &self,
         key: &SnapKey,
         snapshot_meta: SnapshotMeta,
+        meta_ext: SnapshotMetaExt,
     ) -> RaftStoreResult<Box<Snapshot>> {
+        self.core.check_recv_budget(&snapshot_meta)?;
         let _lock = self.core.registry.rl();
         let base = &self.core.base;
-        let f = Snapshot::new_for_receiving(base, key, &self.core, snapshot_meta)?;
+        let f = Snapshot::new_for_receiving(base, key, &self.core, snapshot_meta, meta_ext)?;
         Ok(Box::new(f))
     }
 
@@ -1725,9 +2912,56 @@ impl SnapManager {
                 key
             ))));
         }
+        if self.core.verify_apply_content_hash {
+            s.verify_content_hash()?;
+        }
         Ok(Box::new(s))
     }
 
+    /// Recompute the aggregate content hash of the snapshot identified by `key`
+    /// from its on-disk CF files and compare it against the value recorded in
+    /// the meta, without applying the snapshot. Lets operators audit stored
+    /// snapshots for silent corruption; fails with the dedicated content-hash
+    /// mismatch error if they diverge.
+    pub fn verify_snapshot(&self, key: &SnapKey) -> RaftStoreResult<()> {
+        let _lock = self.core.registry.rl();
+        let base = &self.core.base;
+        let s = Snapshot::new_for_applying(base, key, &self.core)?;
+        if !s.exists() {
+            return Err(RaftStoreError::Other(From::from(format!(
+                "snapshot of {:?} not exists.",
+                key
+            ))));
+        }
+        s.verify_content_hash()
+    }
+
+    /// Run [`Self::verify_snapshot`] once for every distinct key known to
+    /// [`Self::list_idle_snap`], giving operators a single entry point to
+    /// audit stored snapshots instead of having to enumerate keys themselves
+    /// first. Only the receiving-side copy of each key is read: a key that
+    /// exists only as a generated (sending) snapshot is reported with the
+    /// not-exists error of [`Self::verify_snapshot`].
+    pub fn verify_all_snapshots(&self) -> io::Result<Vec<(SnapKey, RaftStoreResult<()>)>> {
+        // `list_idle_snap` yields one entry per direction (sending/receiving)
+        // of a key, but verification only ever reads the receiving side, so
+        // collapse to the distinct keys first to avoid verifying twice.
+        let mut keys: Vec<SnapKey> = self
+            .list_idle_snap()?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        keys.dedup();
+        Ok(keys
+            .into_iter()
+            .map(|key| {
+                let result = self.verify_snapshot(&key);
+                (key, result)
+            })
+            .collect())
+    }
+
     pub fn meta_file_exist(&self, key: &SnapKey) -> RaftStoreResult<()> {
         let _lock = self.core.registry.rl();
         let base = &self.core.base;
@@ -1784,6 +3018,16 @@ impl SnapManager {
             .get_actual_max_per_file_size(allow_multi_files_snapshot)
     }
 
+    pub fn get_build_concurrency(&self) -> usize {
+        self.core.build_concurrency.load(Ordering::Relaxed).max(1)
+    }
+
+    pub fn set_build_concurrency(&self, concurrency: usize) {
+        self.core
+            .build_concurrency
+            .store(concurrency.max(1), Ordering::Release);
+    }
+
     pub fn set_enable_multi_snapshot_files(&mut self, enable_multi_snapshot_files: bool) {
         self.core
             .enable_multi_snapshot_files
@@ -1890,6 +3134,8 @@ impl SnapManager {
             sending_count: sending_cnt,
             receiving_count: receiving_cnt,
             stats,
+            full_sends: self.core.full_sends.load(Ordering::SeqCst),
+            incremental_sends: self.core.incremental_sends.load(Ordering::SeqCst),
         }
     }
 
@@ -1938,8 +3184,88 @@ impl SnapManagerCore {
         Ok(total_size)
     }
 
+    // Record that one more incremental snapshot depends on `base`.
+    fn register_base_dependency(&self, base: &SnapKey) {
+        *self.base_dependents.wl().entry(base.clone()).or_insert(0) += 1;
+    }
+
+    // Drop one incremental dependency on `base`, removing the entry at zero.
+    fn release_base_dependency(&self, base: &SnapKey) {
+        let mut deps = self.base_dependents.wl();
+        if let Some(cnt) = deps.get_mut(base) {
+            *cnt -= 1;
+            if *cnt == 0 {
+                deps.remove(base);
+            }
+        }
+    }
+
+    fn has_base_dependents(&self, key: &SnapKey) -> bool {
+        self.base_dependents.rl().get(key).map_or(false, |c| *c > 0)
+    }
+
+    // Reject a received snapshot whose declared metadata exceeds the configured
+    // hardened-unpack budget before any CF file is written to disk. Any
+    // per-file size over the claimed `max_per_file_size`, a total over
+    // `max_recv_snap_size`, or more than `max_recv_file_count` files aborts with
+    // `Error::SnapshotTooLarge`.
+    fn check_recv_budget(&self, snapshot_meta: &SnapshotMeta) -> RaftStoreResult<()> {
+        let cf_files = snapshot_meta.get_cf_files();
+        // Name allowlist: a CF file may only reference a known snapshot CF and
+        // must not smuggle path separators or `..` components that could be
+        // turned into a traversal when the name is joined onto the snap dir.
+        for cf_file in cf_files {
+            let cf = cf_file.get_cf();
+            if !SNAPSHOT_CFS.contains(&cf) {
+                return Err(RaftStoreError::Snapshot(Error::UnsafeSnapshotEntry(
+                    format!("cf {} is not an allowed snapshot cf", cf),
+                )));
+            }
+            if cf.contains('/') || cf.contains('\\') || cf.contains("..") {
+                return Err(RaftStoreError::Snapshot(Error::UnsafeSnapshotEntry(
+                    format!("cf name {} contains an unsafe path component", cf),
+                )));
+            }
+        }
+        if self.max_recv_file_count > 0 && cf_files.len() > self.max_recv_file_count {
+            return Err(RaftStoreError::Snapshot(Error::SnapshotTooLarge(format!(
+                "declared {} files exceeds cap {}",
+                cf_files.len(),
+                self.max_recv_file_count
+            ))));
+        }
+        let max_per_file = self.max_per_file_size.load(Ordering::Relaxed);
+        let mut total: u64 = 0;
+        for cf_file in cf_files {
+            let size = cf_file.get_size();
+            if max_per_file != u64::MAX && size > max_per_file {
+                return Err(RaftStoreError::Snapshot(Error::SnapshotTooLarge(format!(
+                    "cf {} declared file size {} exceeds per-file cap {}",
+                    cf_file.get_cf(),
+                    size,
+                    max_per_file
+                ))));
+            }
+            total = total.saturating_add(size);
+            if self.max_recv_snap_size > 0 && total > self.max_recv_snap_size {
+                return Err(RaftStoreError::Snapshot(Error::SnapshotTooLarge(format!(
+                    "declared total size {} exceeds budget {}",
+                    total, self.max_recv_snap_size
+                ))));
+            }
+        }
+        Ok(())
+    }
+
     // Return true if it successfully delete the specified snapshot.
     fn delete_snapshot(&self, key: &SnapKey, snap: &Snapshot, check_entry: bool) -> bool {
+        if self.has_base_dependents(key) {
+            info!(
+                "skip to delete snapshot since an incremental snapshot depends on it";
+                "snapshot" => %snap.path(),
+            );
+            return false;
+        }
         let registry = self.registry.rl();
         if check_entry {
             if let Some(e) = registry.get(key) {
@@ -1960,6 +3286,12 @@ impl SnapManagerCore {
             return false;
         }
         snap.delete();
+        // Once an incremental snapshot is gone, its base no longer needs to be
+        // pinned on its behalf; drop the dependency so the base becomes GC
+        // eligible again when nothing else references it.
+        if let Some(base) = &snap.base_key {
+            self.release_base_dependency(base);
+        }
         true
     }
 
@@ -1993,6 +3325,11 @@ impl SnapManagerCore {
             let file = Path::new(&file_paths[i]);
             let (checksum, size) = calc_checksum_and_size(file, mgr)?;
             cf_file.add_file_with_size_checksum(i, size, checksum);
+            if cf_file.archive_format != snap_io::ArchiveFormat::None {
+                // The wire checksum is taken over the on-disk bytes as-is so the
+                // receiver can validate transfer integrity before decoding.
+                cf_file.add_wire_size_checksum(i, get_file_size(file)?, calc_crc32(file)?);
+            }
         }
         Ok(())
     }
@@ -2008,6 +3345,16 @@ impl SnapManagerCore {
         u64::MAX
     }
 
+    pub fn get_build_concurrency(&self) -> usize {
+        self.build_concurrency.load(Ordering::Relaxed).max(1)
+    }
+
+    // Upper bound on how many CFs `do_build` builds concurrently. `1` keeps the
+    // historical CF-at-a-time behaviour.
+    pub fn get_max_build_concurrency(&self) -> usize {
+        self.max_build_concurrency.max(1)
+    }
+
     pub fn can_apply_cf_without_ingest(&self, cf_size: u64, cf_kvs: u64) -> bool {
         fail_point!("apply_cf_without_ingest_false", |_| { false });
         if self.min_ingest_cf_size == 0 {
@@ -2026,7 +3373,21 @@ pub struct SnapManagerBuilder {
     max_total_size: u64,
     max_per_file_size: u64,
     enable_multi_snapshot_files: bool,
+    build_concurrency: usize,
+    max_build_concurrency: usize,
+    snap_io_concurrency: usize,
+    max_snapshots_per_region: usize,
+    max_snapshots_to_retain: usize,
+    max_recv_snap_size: u64,
+    max_recv_file_count: usize,
+    verify_apply_content_hash: bool,
+    verify_apply_fs_security: Option<bool>,
+    archive_compression_level: i32,
+    enable_streaming_apply: bool,
+    compression_policy: snap_io::SstCompressionPolicy,
+    archive_format: snap_io::ArchiveFormat,
     enable_receive_tablet_snapshot: bool,
+    enable_archive_transfer: bool,
     key_manager: Option<Arc<DataKeyManager>>,
     min_ingest_snapshot_size: u64,
     min_ingest_snapshot_kvs: u64,
@@ -2051,10 +3412,110 @@ impl SnapManagerBuilder {
         self.enable_multi_snapshot_files = enabled;
         self
     }
+    pub fn build_concurrency(mut self, concurrency: usize) -> SnapManagerBuilder {
+        self.build_concurrency = concurrency;
+        self
+    }
+    pub fn max_build_concurrency(mut self, concurrency: usize) -> SnapManagerBuilder {
+        self.max_build_concurrency = concurrency;
+        self
+    }
+    /// Size of the worker pool that `build()` uses to process independent CF
+    /// files concurrently. A value of `1` keeps the build loop single-threaded
+    /// on the calling thread, preserving the deterministic behaviour existing
+    /// tests rely on. Apply-side concurrency is not set here; it comes from
+    /// `ApplyOptions::apply_concurrency`. Alias for
+    /// [`Self::max_build_concurrency`].
+    pub fn build_threads(self, threads: usize) -> SnapManagerBuilder {
+        self.max_build_concurrency(threads.max(1))
+    }
+    pub fn snap_io_concurrency(mut self, concurrency: usize) -> SnapManagerBuilder {
+        self.snap_io_concurrency = concurrency;
+        self
+    }
+    /// Number of scoped worker threads used to fan out per-CF-file work on the
+    /// send/apply paths (decrypter-reader setup, checksum verification, ingest
+    /// selection). `0`/`1` keeps those loops sequential on the calling thread.
+    pub fn snap_io_threads(mut self, threads: usize) -> SnapManagerBuilder {
+        self.snap_io_concurrency = threads;
+        self
+    }
+    pub fn max_snapshots_per_region(mut self, limit: usize) -> SnapManagerBuilder {
+        self.max_snapshots_per_region = limit;
+        self
+    }
+    pub fn max_snapshots_to_retain(mut self, limit: usize) -> SnapManagerBuilder {
+        self.max_snapshots_to_retain = limit;
+        self
+    }
+    pub fn max_recv_snap_size(mut self, bytes: u64) -> SnapManagerBuilder {
+        self.max_recv_snap_size = bytes;
+        self
+    }
+    pub fn max_recv_file_count(mut self, count: usize) -> SnapManagerBuilder {
+        self.max_recv_file_count = count;
+        self
+    }
+    /// Toggle recomputing and verifying the aggregate snapshot content digest on
+    /// the apply path. Off by default.
+    pub fn verify_apply_content_hash(mut self, enabled: bool) -> SnapManagerBuilder {
+        self.verify_apply_content_hash = enabled;
+        self
+    }
+    /// Audit filesystem permissions of staged ingestion clones on the apply
+    /// path (see `sst_importer::verify_fs_security`). `None` disables the
+    /// check; `Some(downgrade_to_warn)` enables it, only logging a warning
+    /// instead of failing the apply when `downgrade_to_warn` is set. Disabled
+    /// by default.
+    pub fn verify_apply_fs_security(mut self, fs_security: Option<bool>) -> SnapManagerBuilder {
+        self.verify_apply_fs_security = fs_security;
+        self
+    }
+    pub fn compression_policy(
+        mut self,
+        policy: snap_io::SstCompressionPolicy,
+    ) -> SnapManagerBuilder {
+        self.compression_policy = policy;
+        self
+    }
+    pub fn archive_format(mut self, format: snap_io::ArchiveFormat) -> SnapManagerBuilder {
+        self.archive_format = format;
+        self
+    }
+    /// Select the CF-file compression codec as a single option, lowering it into
+    /// the wire [`snap_io::ArchiveFormat`] and its effort level.
+    pub fn compression_format(
+        mut self,
+        format: snap_io::CompressionFormat,
+    ) -> SnapManagerBuilder {
+        let (archive_format, level) = format.into_parts();
+        self.archive_format = archive_format;
+        self.archive_compression_level = level;
+        self
+    }
+    /// Select the snapshot CF-file compression codec, e.g.
+    /// `.snapshot_compression(CompressionFormat::Zstd { level })`. Thin alias for
+    /// [`Self::compression_format`], spelled the way deployments configure it.
+    pub fn snapshot_compression(self, format: snap_io::CompressionFormat) -> SnapManagerBuilder {
+        self.compression_format(format)
+    }
+    pub fn archive_compression_level(mut self, level: i32) -> SnapManagerBuilder {
+        self.archive_compression_level = level;
+        self
+    }
+    pub fn enable_streaming_apply(mut self, enabled: bool) -> SnapManagerBuilder {
+        self.enable_streaming_apply = enabled;
+        self
+    }
     pub fn enable_receive_tablet_snapshot(mut self, enabled: bool) -> SnapManagerBuilder {
         self.enable_receive_tablet_snapshot = enabled;
         self
     }
+    /// Negotiate single-archive (vs directory) tablet snapshot transfer.
+    pub fn enable_archive_transfer(mut self, enabled: bool) -> SnapManagerBuilder {
+        self.enable_archive_transfer = enabled;
+        self
+    }
     pub fn min_ingest_snapshot_limit(mut self, bytes: ReadableSize) -> SnapManagerBuilder {
         self.min_ingest_snapshot_size = bytes.0;
         // Keeps the same assumptions in region size, "Assume the average size of KVs is
@@ -2080,10 +3541,24 @@ impl SnapManagerBuilder {
         };
         let path = path.into();
         assert!(!path.is_empty());
+        // Canonicalize the base path so two differently-spelled paths (relative
+        // vs absolute, via a symlink, or with extra components) that resolve to
+        // the same physical directory produce a single manager keyed on the
+        // canonical form, rather than two racing over the same files. The
+        // `no-canonicalize-path` feature is an escape hatch for filesystems
+        // where canonicalization fails; callers must then supply consistent
+        // paths themselves.
+        let path = canonicalize_snap_base(path);
         let mut path_v2 = path.clone();
         path_v2.push_str("_v2");
         let tablet_snap_manager = if self.enable_receive_tablet_snapshot {
-            Some(TabletSnapManager::new(&path_v2, self.key_manager.clone()).unwrap())
+            let mut mgr = TabletSnapManager::new(&path_v2, self.key_manager.clone()).unwrap();
+            mgr.set_archive_transfer(self.enable_archive_transfer);
+            // Arm the hardened-unpack budget from the same receive caps the
+            // classic manager uses, so an oversized or file-count-bomb tablet
+            // snapshot is rejected during `unpack_snapshot`.
+            mgr.set_recv_limits(self.max_recv_file_count, self.max_recv_snap_size);
+            Some(mgr)
         } else {
             None
         };
@@ -2099,6 +3574,22 @@ impl SnapManagerBuilder {
                 enable_multi_snapshot_files: Arc::new(AtomicBool::new(
                     self.enable_multi_snapshot_files,
                 )),
+                build_concurrency: Arc::new(AtomicUsize::new(self.build_concurrency.max(1))),
+                max_build_concurrency: self.max_build_concurrency.max(1),
+                compression_policy: self.compression_policy,
+                archive_format: self.archive_format,
+                archive_compression_level: self.archive_compression_level,
+                enable_streaming_apply: self.enable_streaming_apply,
+                base_dependents: Default::default(),
+                full_sends: Arc::default(),
+                incremental_sends: Arc::default(),
+                snap_io_concurrency: self.snap_io_concurrency.max(1),
+                max_snapshots_per_region: self.max_snapshots_per_region,
+                max_snapshots_to_retain: self.max_snapshots_to_retain,
+                max_recv_snap_size: self.max_recv_snap_size,
+                max_recv_file_count: self.max_recv_file_count,
+                verify_apply_content_hash: self.verify_apply_content_hash,
+                verify_apply_fs_security: self.verify_apply_fs_security,
                 max_total_size: Arc::new(AtomicU64::new(max_total_size)),
                 stats: Default::default(),
                 min_ingest_cf_size: self.min_ingest_snapshot_size,
@@ -2112,6 +3603,28 @@ impl SnapManagerBuilder {
     }
 }
 
+/// Resolve `path` to its canonical form so every spelling of the same physical
+/// directory maps to one manager. The directory is created first so
+/// canonicalization can resolve it; if it still fails (or the feature disables
+/// it), the original path is returned unchanged.
+#[cfg(not(feature = "no-canonicalize-path"))]
+fn canonicalize_snap_base(path: String) -> String {
+    let _ = file_system::create_dir_all(&path);
+    match std::fs::canonicalize(&path) {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(e) => {
+            warn!("failed to canonicalize snap dir, using path as-is";
+                "path" => %path, "err" => ?e);
+            path
+        }
+    }
+}
+
+#[cfg(feature = "no-canonicalize-path")]
+fn canonicalize_snap_base(path: String) -> String {
+    path
+}
+
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct TabletSnapKey {
     pub region_id: u64,
@@ -2140,6 +3653,12 @@ impl TabletSnapKey {
     pub fn from_path<T: Into<PathBuf>>(path: T) -> Result<TabletSnapKey> {
         let path = path.into();
         let name = path.file_name().unwrap().to_str().unwrap();
+        // A live sibling `.lock` marker means the snapshot is still being
+        // written or half-deleted; treat it as nonexistent.
+        let lock = path.with_file_name(format!("{}{}", name, LOCK_FILE_SUFFIX));
+        if lock.exists() {
+            return Err(box_err!("tablet snapshot {} is locked (incomplete)", name));
+        }
         let numbers: Vec<u64> = name
             .split('_')
             .skip(1)
@@ -2152,6 +3671,31 @@ impl TabletSnapKey {
             numbers[0], numbers[1], numbers[2], numbers[3],
         ))
     }
+
+    /// File name of this snapshot when packed into a single archive for
+    /// transfer, e.g. `gen_1_2_3_4.tsnap`.
+    pub fn to_archive_name(&self) -> String {
+        format!("{}_{}{}", SNAP_GEN_PREFIX, self, ARCHIVE_FILE_SUFFIX)
+    }
+
+    /// Parse a `TabletSnapKey` from an archive file name produced by
+    /// [`Self::to_archive_name`].
+    pub fn from_archive_name(name: &str) -> Result<TabletSnapKey> {
+        let stem = name
+            .strip_suffix(ARCHIVE_FILE_SUFFIX)
+            .ok_or_else(|| box_err!("invalid tablet snapshot archive name:{}", name))?;
+        let numbers: Vec<u64> = stem
+            .split('_')
+            .skip(1)
+            .filter_map(|s| s.parse().ok())
+            .collect();
+        if numbers.len() < 4 {
+            return Err(box_err!("invalid tablet snapshot archive name:{}", name));
+        }
+        Ok(TabletSnapKey::new(
+            numbers[0], numbers[1], numbers[2], numbers[3],
+        ))
+    }
 }
 
 impl Display for TabletSnapKey {
@@ -2164,6 +3708,41 @@ impl Display for TabletSnapKey {
     }
 }
 
+/// An incremental tablet snapshot identified by the `TabletSnapKey` of the
+/// snapshot being built plus the `idx` of the full (base) snapshot it is
+/// layered on. Only the CF key ranges changed since `base_idx` are carried; on
+/// apply the base tablet is restored first and the delta overlaid on top. An
+/// incremental snapshot is only valid while its base is still present locally.
+#[derive(Eq, PartialEq, Clone, Hash, Debug)]
+pub struct IncrementalTabletSnapKey {
+    pub key: TabletSnapKey,
+    pub base_idx: u64,
+}
+
+impl IncrementalTabletSnapKey {
+    #[inline]
+    pub fn new(key: TabletSnapKey, base_idx: u64) -> IncrementalTabletSnapKey {
+        IncrementalTabletSnapKey { key, base_idx }
+    }
+
+    /// The key of the full snapshot this incremental depends on. Same region,
+    /// peer and term; the base `idx` identifies the materialized checkpoint.
+    pub fn base_key(&self) -> TabletSnapKey {
+        TabletSnapKey::new(
+            self.key.region_id,
+            self.key.to_peer,
+            self.key.term,
+            self.base_idx,
+        )
+    }
+}
+
+impl Display for IncrementalTabletSnapKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_base{}", self.key, self.base_idx)
+    }
+}
+
 pub struct ReceivingGuard<'a> {
     receiving: &'a Mutex<Vec<TabletSnapKey>>,
     key: TabletSnapKey,
@@ -2177,11 +3756,95 @@ impl Drop for ReceivingGuard<'_> {
     }
 }
 
+/// Pack every regular file under `dir` into a single stored (uncompressed)
+/// archive at `archive`. Each entry is `<u32 name_len><name><u64 data_len>
+/// <data>`; SST payloads are already compressed, so no further compression is
+/// applied. Entries are emitted in sorted name order so the output is stable.
+fn pack_dir_to_archive(dir: &Path, archive: &Path) -> Result<()> {
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for entry in file_system::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            entries.push((name.to_owned(), path));
+        }
+    }
+    entries.sort();
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(archive)?;
+    for (name, path) in entries {
+        let data = file_system::read(&path)?;
+        out.write_all(&(name.len() as u32).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&(data.len() as u64).to_le_bytes())?;
+        out.write_all(&data)?;
+    }
+    out.sync_all()?;
+    Ok(())
+}
+
+/// Inverse of [`pack_dir_to_archive`]: materialize each archived entry as a file
+/// under `dir`. Entry names are validated against path traversal before any
+/// write.
+fn unpack_archive_to_dir(archive: &Path, dir: &Path) -> Result<()> {
+    file_system::create_dir_all(dir)?;
+    let buf = file_system::read(archive)?;
+    let mut pos = 0usize;
+    while pos < buf.len() {
+        if pos + 4 > buf.len() {
+            return Err(box_err!("truncated archive header"));
+        }
+        let name_len = u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap()) as usize;
+        pos += 4;
+        if pos + name_len > buf.len() {
+            return Err(box_err!("truncated archive name"));
+        }
+        let name = str::from_utf8(&buf[pos..pos + name_len])
+            .map_err(|e| Error::Other(format!("invalid archive entry name: {}", e).into()))?
+            .to_owned();
+        pos += name_len;
+        if name.contains('/') || name.contains('\\') || name.contains("..") {
+            return Err(Error::UnsafeSnapshotEntry(format!(
+                "unsafe archive entry {}",
+                name
+            )));
+        }
+        if pos + 8 > buf.len() {
+            return Err(box_err!("truncated archive length"));
+        }
+        let data_len = u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap()) as usize;
+        pos += 8;
+        if pos + data_len > buf.len() {
+            return Err(box_err!("truncated archive data"));
+        }
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join(&name))?;
+        f.write_all(&buf[pos..pos + data_len])?;
+        f.sync_all()?;
+        pos += data_len;
+    }
+    sync_dir(dir)?;
+    Ok(())
+}
+
+/// Wall-clock age of a snapshot directory, derived from its modified time.
+fn dir_age(path: &Path) -> io::Result<time::Duration> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    modified
+        .elapsed()
+        .map_err(|e| io::Error::new(ErrorKind::Other, e))
+}
+
 /// `TabletSnapManager` manager tablet snapshot and shared between raftstore v2.
 /// It's similar `SnapManager`, but simpler in tablet version.
-///
-///  TODO:
-///     - clean up expired tablet checkpointer
 #[derive(Clone)]
 pub struct TabletSnapManager {
     // directory to store snapfile.
@@ -2191,6 +3854,26 @@ pub struct TabletSnapManager {
     stats: Arc<Mutex<HashMap<TabletSnapKey, (Instant, SnapshotStat)>>>,
     sending_count: Arc<AtomicUsize>,
     recving_count: Arc<AtomicUsize>,
+    // Archive codec applied to generated CF files before they are sent and
+    // transparently reversed on receive. `ArchiveFormat::None` keeps files raw
+    // for peers that don't advertise format support; the level tunes the codec
+    // effort (`0` means the codec default).
+    archive_format: snap_io::ArchiveFormat,
+    archive_compression_level: i32,
+    // Hardened-unpack caps for received snapshots. `0` disables the
+    // corresponding check.
+    max_recv_file_count: usize,
+    max_recv_total_bytes: u64,
+    // Retention policy for generated (`gen_*`) checkpoints. At most
+    // `max_gen_checkpoints` most-recent checkpoints are kept per region; any
+    // older one, or any checkpoint directory older than `gen_checkpoint_ttl`,
+    // is trashed by [`Self::gc_gen_checkpoints`]. A count of `0` disables the
+    // count cap and a zero TTL disables the age cap.
+    max_gen_checkpoints: usize,
+    gen_checkpoint_ttl: time::Duration,
+    // When set, snapshots are transferred as a single stored archive
+    // (`TabletSnapKey::to_archive_name`) rather than a directory tree.
+    archive_transfer: bool,
 }
 
 impl TabletSnapManager {
@@ -2217,9 +3900,54 @@ impl TabletSnapManager {
             stats: Arc::default(),
             sending_count: Arc::default(),
             recving_count: Arc::default(),
+            archive_format: snap_io::ArchiveFormat::None,
+            archive_compression_level: 0,
+            max_recv_file_count: 0,
+            max_recv_total_bytes: 0,
+            max_gen_checkpoints: 0,
+            gen_checkpoint_ttl: time::Duration::ZERO,
+            archive_transfer: false,
         })
     }
 
+    /// Toggle single-archive transfer mode (see [`Self::pack_snapshot`]).
+    pub fn set_archive_transfer(&mut self, enabled: bool) {
+        self.archive_transfer = enabled;
+    }
+
+    #[inline]
+    pub fn archive_transfer_enabled(&self) -> bool {
+        self.archive_transfer
+    }
+
+    /// Configure the retention policy enforced by [`Self::gc_gen_checkpoints`]:
+    /// keep at most `max_checkpoints` most-recent generated checkpoints per
+    /// region (`0` keeps them all) and trash any checkpoint directory older
+    /// than `ttl` (a zero `ttl` disables the age cap).
+    pub fn set_gen_checkpoint_retention(&mut self, max_checkpoints: usize, ttl: time::Duration) {
+        self.max_gen_checkpoints = max_checkpoints;
+        self.gen_checkpoint_ttl = ttl;
+    }
+
+    /// Configure the hardened-unpack caps enforced by [`Self::harden_unpack`].
+    pub fn set_recv_limits(&mut self, max_file_count: usize, max_total_bytes: u64) {
+        self.max_recv_file_count = max_file_count;
+        self.max_recv_total_bytes = max_total_bytes;
+    }
+
+    /// Select the archive codec (and its effort level) applied to generated CF
+    /// files. Defaults to [`snap_io::ArchiveFormat::None`]; a typical
+    /// compressed deployment uses `Zstd` with a small positive level.
+    pub fn set_snap_compression(&mut self, format: snap_io::ArchiveFormat, level: i32) {
+        self.archive_format = format;
+        self.archive_compression_level = level;
+    }
+
+    #[inline]
+    pub fn snap_compression(&self) -> (snap_io::ArchiveFormat, i32) {
+        (self.archive_format, self.archive_compression_level)
+    }
+
     pub fn begin_snapshot(&self, key: TabletSnapKey, start: Instant, generate_duration_sec: u64) {
         let mut stat = SnapshotStat::default();
         stat.set_generate_duration_sec(generate_duration_sec);
@@ -2252,6 +3980,8 @@ impl TabletSnapManager {
             sending_count: self.sending_count.load(Ordering::SeqCst),
             receiving_count: self.recving_count.load(Ordering::SeqCst),
             stats,
+            full_sends: 0,
+            incremental_sends: 0,
         }
     }
 
@@ -2270,8 +4000,39 @@ impl TabletSnapManager {
         PathBuf::from(&self.base).join(prefix)
     }
 
+    /// Generation path for an incremental snapshot, encoding the base idx in the
+    /// directory name (e.g. `gen_<region>_<peer>_<term>_<idx>_base<baseidx>`).
+    pub fn tablet_gen_path_incremental(&self, key: &IncrementalTabletSnapKey) -> PathBuf {
+        let prefix = format!("{}_{}", SNAP_GEN_PREFIX, key);
+        PathBuf::from(&self.base).join(prefix)
+    }
+
+    /// Final receive path for an incremental snapshot, mirroring the base-idx
+    /// naming of [`tablet_gen_path_incremental`].
+    pub fn final_recv_path_incremental(&self, key: &IncrementalTabletSnapKey) -> PathBuf {
+        let prefix = format!("{}_{}", SNAP_REV_PREFIX, key);
+        PathBuf::from(&self.base).join(prefix)
+    }
+
+    /// Whether the base full snapshot an incremental depends on is still present
+    /// locally. An incremental snapshot must never be applied unless this holds;
+    /// callers should otherwise fall back to requesting a full snapshot.
+    pub fn base_exists(&self, key: &IncrementalTabletSnapKey) -> bool {
+        self.tablet_gen_path(&key.base_key()).exists()
+    }
+
     pub fn delete_snapshot(&self, key: &TabletSnapKey) -> bool {
         let path = self.tablet_gen_path(key);
+        // Refuse to drop a base snapshot while an incremental on disk still
+        // declares it as its base, otherwise that incremental becomes
+        // unappliable.
+        if self.has_incremental_dependents(key) {
+            info!(
+                "skip to delete tablet snapshot since an incremental depends on it";
+                "path" => %path.display(),
+            );
+            return false;
+        }
         debug!("delete tablet snapshot file";"path" => %path.display());
         if path.exists() {
             if let Err(e) = encryption::trash_dir_all(&path, self.key_manager.as_deref()) {
@@ -2286,6 +4047,287 @@ impl TabletSnapManager {
         true
     }
 
+    /// Trash expired generated checkpoints according to the configured
+    /// retention policy (see [`Self::set_gen_checkpoint_retention`]), returning
+    /// the keys that were purged so the caller can log or account for them.
+    ///
+    /// Candidates come from [`Self::list_snapshot`]; within each region they are
+    /// ordered newest-first by applied `idx`, everything beyond
+    /// `max_gen_checkpoints` is dropped, and any remaining checkpoint whose
+    /// directory is older than `gen_checkpoint_ttl` is dropped too. Purging goes
+    /// through [`Self::delete_snapshot`], so a checkpoint still referenced by an
+    /// on-disk incremental is left untouched.
+    pub fn gc_gen_checkpoints(&self) -> Result<Vec<TabletSnapKey>> {
+        if self.max_gen_checkpoints == 0 && self.gen_checkpoint_ttl.is_zero() {
+            return Ok(Vec::new());
+        }
+        // Group generated checkpoints by region, keeping each directory path so
+        // the age cap can read its modified time.
+        let mut by_region: HashMap<u64, Vec<(TabletSnapKey, PathBuf)>> = HashMap::default();
+        for path in self.list_snapshot()? {
+            if let Ok(key) = TabletSnapKey::from_path(&path) {
+                by_region.entry(key.region_id).or_default().push((key, path));
+            }
+        }
+        let mut purged = Vec::new();
+        for (_, mut snaps) in by_region {
+            // Newest first: a larger applied idx (ties broken by term) is more
+            // recent.
+            snaps.sort_by(|(a, _), (b, _)| b.idx.cmp(&a.idx).then_with(|| b.term.cmp(&a.term)));
+            for (rank, (key, path)) in snaps.iter().enumerate() {
+                let over_count =
+                    self.max_gen_checkpoints > 0 && rank >= self.max_gen_checkpoints;
+                let expired = !self.gen_checkpoint_ttl.is_zero()
+                    && dir_age(path).map_or(false, |age| age > self.gen_checkpoint_ttl);
+                if (over_count || expired) && self.delete_snapshot(key) {
+                    purged.push(key.clone());
+                }
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Run [`Self::gc_gen_checkpoints`] on a background thread every
+    /// `interval` until `stop` is set, logging what it purges each round and,
+    /// via [`Self::all_tablet_snapshots`], the total inventory left behind so
+    /// an operator watching the log can see checkpoint growth independent of
+    /// what any single round purged. `gc_gen_checkpoints` itself stays
+    /// schedule-agnostic, returning the purged keys rather than owning a loop,
+    /// so a caller that already runs its own periodic tasks can invoke it
+    /// directly instead of spawning this thread; this is the minimal driver
+    /// for callers that don't.
+    pub fn spawn_checkpoint_gc(
+        &self,
+        interval: time::Duration,
+        stop: Arc<AtomicBool>,
+    ) -> io::Result<thread::JoinHandle<()>> {
+        let mgr = self.clone();
+        thread::Builder::new()
+            .name("tablet-checkpoint-gc".to_string())
+            .spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match mgr.gc_gen_checkpoints() {
+                        Ok(purged) if !purged.is_empty() => {
+                            info!("purged expired tablet checkpoints"; "count" => purged.len());
+                        }
+                        Ok(_) => {}
+                        Err(e) => error!("tablet checkpoint gc failed"; "err" => ?e),
+                    }
+                    match mgr.all_tablet_snapshots() {
+                        Ok(all) => debug!("tablet snapshot inventory"; "count" => all.len()),
+                        Err(e) => error!("failed to list tablet snapshots"; "err" => ?e),
+                    }
+                    thread::sleep(interval);
+                }
+            })
+    }
+
+    /// Write a content-address manifest for the snapshot materialized under
+    /// `dir`, mapping each CF file name to a crc32 salted with `key`. The
+    /// manifest travels with the snapshot so the receiver can independently
+    /// re-verify it through [`Self::verify_received`].
+    pub fn write_manifest(&self, dir: &Path, key: &TabletSnapKey) -> Result<()> {
+        let mut body = String::new();
+        for (name, checksum) in self.hash_dir(dir, key)? {
+            body.push_str(&format!("{} {}\n", name, checksum));
+        }
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join(TABLET_SNAP_MANIFEST))?;
+        f.write_all(body.as_bytes())?;
+        f.sync_all()?;
+        sync_dir(dir)?;
+        Ok(())
+    }
+
+    /// Independently re-verify a received snapshot against the manifest shipped
+    /// with it, before the caller ingests anything. Every file under
+    /// `final_recv_path(key)` is re-hashed (content-addressed, salted with the
+    /// `TabletSnapKey`) and matched against its manifest entry; a missing,
+    /// extra, or mismatched file fails with [`Error::ContentHashMismatch`]
+    /// naming the offending CF file, so a silently-corrupted transfer is caught
+    /// here rather than surfacing as inconsistent data after ingestion.
+    pub fn verify_received(&self, key: &TabletSnapKey) -> Result<()> {
+        let dir = self.final_recv_path(key);
+        let raw = file_system::read(dir.join(TABLET_SNAP_MANIFEST)).map_err(|e| {
+            Error::ContentHashMismatch(format!("missing manifest for {}: {}", dir.display(), e))
+        })?;
+        let manifest = str::from_utf8(&raw)
+            .map_err(|e| Error::ContentHashMismatch(format!("corrupt manifest: {}", e)))?;
+        let mut expected: HashMap<String, u32> = HashMap::default();
+        for line in manifest.lines() {
+            let mut it = line.split_whitespace();
+            if let (Some(name), Some(cs)) = (it.next(), it.next()) {
+                let checksum = cs.parse::<u32>().map_err(|e| {
+                    Error::ContentHashMismatch(format!("invalid manifest line `{}`: {}", line, e))
+                })?;
+                expected.insert(name.to_owned(), checksum);
+            }
+        }
+        for (name, checksum) in self.hash_dir(&dir, key)? {
+            match expected.remove(&name) {
+                Some(exp) if exp == checksum => {}
+                Some(exp) => {
+                    return Err(Error::ContentHashMismatch(format!(
+                        "cf file {} checksum mismatch, expect {}, got {}",
+                        name, exp, checksum
+                    )));
+                }
+                None => {
+                    return Err(Error::ContentHashMismatch(format!(
+                        "cf file {} is not listed in the manifest",
+                        name
+                    )));
+                }
+            }
+        }
+        if let Some((name, _)) = expected.into_iter().next() {
+            return Err(Error::ContentHashMismatch(format!(
+                "cf file {} listed in the manifest is missing on disk",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    // Content-address every regular file under `dir` (excluding the manifest
+    // itself), salting each crc32 with `key` so identical bytes under a
+    // different key hash differently. Entries come back sorted by name, giving
+    // an order-independent, stable digest on both the sender and receiver.
+    fn hash_dir(&self, dir: &Path, key: &TabletSnapKey) -> Result<Vec<(String, u32)>> {
+        let mut entries = Vec::new();
+        for entry in file_system::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(n) if n != TABLET_SNAP_MANIFEST => n.to_owned(),
+                _ => continue,
+            };
+            let mut hasher = crc32fast::Hasher::new();
+            hasher.update(key.to_string().as_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update(&file_system::read(&path)?);
+            entries.push((name, hasher.finalize()));
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    // There is no shared content-addressed blob store with dedup/refcount GC
+    // across the tablet snapshots managed here. That was built once (a
+    // `dedup_into_blob_store`/`gc_blob_store` pair hardlinking identical SST
+    // files into `.blobs/`), but it was reverted instead of wired into
+    // `pack_snapshot`/`unpack_snapshot` below because it directly conflicts
+    // with `sst_importer::reset_sst_global_seqno`'s `nlink > 1` guard: a
+    // dedup'd hardlink is by construction shared (`nlink > 1`), so the very
+    // first peer that needs to retry a partial ingest of a deduped file would
+    // be permanently refused and stuck. Shipping dedup for real needs that
+    // retry path to copy-on-write the blob (materialize a private copy before
+    // patching the seqno, then drop the shared refcount) instead of patching
+    // the shared file in place; that's a correctness-sensitive change to the
+    // retry path, not a follow-up to the blob store itself, so it wasn't
+    // built blind here.
+    //
+    /// Pack the generated snapshot `key` into a single stored archive alongside
+    /// the snap dir and return its path. Used when the peer negotiates archive
+    /// (rather than directory) transfer, cutting per-file syscall overhead on
+    /// high-latency links.
+    pub fn pack_snapshot(&self, key: &TabletSnapKey) -> Result<PathBuf> {
+        let dir = self.tablet_gen_path(key);
+        // Ship a content manifest inside the archive so the receiver can
+        // independently re-verify every CF file against it (see
+        // [`Self::verify_received`]).
+        self.write_manifest(&dir, key)?;
+        let archive = self.base.join(key.to_archive_name());
+        pack_dir_to_archive(&dir, &archive)?;
+        Ok(archive)
+    }
+
+    /// Audit the `tmp_recv_path` staging directory for `key` with
+    /// [`Self::harden_unpack`] and, if it passes, promote it to
+    /// `final_recv_path` atomically. Shared by both receive styles so neither
+    /// one can land a snapshot without the count/size/symlink audit: a failed
+    /// audit or an already-landed snapshot drops the staging directory
+    /// instead of promoting it.
+    fn harden_and_promote(&self, key: &TabletSnapKey) -> Result<()> {
+        let tmp = self.tmp_recv_path(key);
+        if let Err(e) = self.harden_unpack(&tmp) {
+            let _ = std::fs::remove_dir_all(&tmp);
+            return Err(e);
+        }
+        let final_path = self.final_recv_path(key);
+        if final_path.exists() {
+            // Another receive already landed this snapshot; drop our staging.
+            let _ = std::fs::remove_dir_all(&tmp);
+            return Ok(());
+        }
+        file_system::rename(&tmp, &final_path)?;
+        sync_dir(&self.base)?;
+        Ok(())
+    }
+
+    /// Unpack a received snapshot `archive` into the final receive (`_v2`)
+    /// layout. The entries are written to the `.tmp` receive directory first and
+    /// the directory is renamed into place atomically, so a partial transfer is
+    /// never observed as a complete snapshot.
+    pub fn unpack_snapshot(&self, key: &TabletSnapKey, archive: &Path) -> Result<()> {
+        let tmp = self.tmp_recv_path(key);
+        if tmp.exists() {
+            let _ = std::fs::remove_dir_all(&tmp);
+        }
+        unpack_archive_to_dir(archive, &tmp)?;
+        self.harden_and_promote(key)?;
+        // Re-verify the promoted snapshot against the manifest packed by the
+        // sender; a corrupt transfer is rejected here rather than after ingest.
+        if let Err(e) = self.verify_received(key) {
+            let _ = std::fs::remove_dir_all(&self.final_recv_path(key));
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Finalize a directory-mode tablet snapshot receive: audit and promote
+    /// the `tmp_recv_path` staging directory the per-file transport wrote
+    /// into, the same way [`Self::unpack_snapshot`] does for a single-archive
+    /// transfer. The default receive path writes each CF file into
+    /// `tmp_recv_path` directly rather than through an archive, so without
+    /// this call it never ran `harden_unpack`'s symlink/count/size audit at
+    /// all. There is no sender-side manifest to re-verify against here
+    /// ([`Self::write_manifest`] is only produced for archive transfer), so
+    /// this stops at the structural audit rather than a content re-hash.
+    pub fn finish_receiving(&self, key: &TabletSnapKey) -> Result<()> {
+        self.harden_and_promote(key)
+    }
+
+    // Whether any incremental snapshot directory on disk is built against
+    // `base` (its name ends with `_base<base.idx>` and shares the same region,
+    // peer and term prefix).
+    fn has_incremental_dependents(&self, base: &TabletSnapKey) -> bool {
+        let suffix = format!("_base{}", base.idx);
+        let prefix = format!("_{}_{}_{}_", base.region_id, base.to_peer, base.term);
+        let entries = match file_system::read_dir(&self.base) {
+            Ok(e) => e,
+            Err(_) => return false,
+        };
+        for entry in entries.flatten() {
+            if let Some(name) = entry.path().file_name().and_then(|n| n.to_str()) {
+                // An incremental built on this key is named
+                // `<gen|rev>_<region>_<peer>_<term>_<own idx>_base<base idx>`.
+                let own = name
+                    .strip_prefix(SNAP_GEN_PREFIX)
+                    .or_else(|| name.strip_prefix(SNAP_REV_PREFIX));
+                if own.map_or(false, |s| s.starts_with(&prefix)) && name.ends_with(&suffix) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
     pub fn list_snapshot(&self) -> Result<Vec<PathBuf>> {
         let mut paths = Vec::new();
         for entry in file_system::read_dir(&self.base)? {
@@ -2306,6 +4348,64 @@ impl TabletSnapManager {
         Ok(paths)
     }
 
+    // The sibling lock marker for a snapshot directory.
+    fn lock_path(&self, dir: &Path) -> PathBuf {
+        let name = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        dir.with_file_name(format!("{}{}", name, LOCK_FILE_SUFFIX))
+    }
+
+    /// Take the lock on the snapshot directory `dir` while it is being
+    /// materialized, so concurrent enumeration via [`Self::all_tablet_snapshots`]
+    /// skips the in-progress snapshot until [`Self::unlock_snapshot`] is called.
+    pub fn lock_snapshot(&self, dir: &Path) -> Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(self.lock_path(dir))?;
+        Ok(())
+    }
+
+    /// Release the lock taken by [`Self::lock_snapshot`], publishing the
+    /// snapshot as complete.
+    pub fn unlock_snapshot(&self, dir: &Path) -> Result<()> {
+        delete_file_if_exist(self.lock_path(dir))?;
+        Ok(())
+    }
+
+    /// Enumerate every complete, unlocked tablet snapshot in the snap dir. Only
+    /// directory names are parsed (via [`TabletSnapKey::from_path`], which skips
+    /// locked snapshots); file contents are not validated, so this is a safe way
+    /// to list recoverable snapshots without racing in-progress writes.
+    pub fn all_tablet_snapshots(&self) -> Result<Vec<TabletSnapKey>> {
+        let mut keys = Vec::new();
+        for entry in file_system::read_dir(&self.base)? {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::from(e)),
+            };
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let is_snapshot = path.file_name().and_then(|n| n.to_str()).map_or(false, |n| {
+                (n.starts_with(SNAP_GEN_PREFIX) || n.starts_with(SNAP_REV_PREFIX))
+                    && !n.ends_with(TMP_FILE_SUFFIX)
+            });
+            if !is_snapshot {
+                continue;
+            }
+            if let Ok(key) = TabletSnapKey::from_path(&path) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
     pub fn total_snap_size(&self) -> Result<u64> {
         let mut total_size = 0;
         for entry in file_system::read_dir(&self.base)? {
@@ -2345,6 +4445,73 @@ impl TabletSnapManager {
         self.base.as_path()
     }
 
+    /// Validate a freshly received snapshot directory before it is promoted
+    /// from `tmp_recv_path` to `final_recv_path`. Every entry must be a regular
+    /// file that is not a symlink, and the cumulative file count and
+    /// uncompressed size are checked against the configured caps. A single bad
+    /// entry aborts with a precise error so the snapshot is rejected before
+    /// `save` rather than after. Path traversal itself is rejected earlier, when
+    /// the archive is unpacked in [`unpack_archive_to_dir`]; the component check
+    /// below is cheap defense-in-depth against a directory built by other means.
+    pub fn harden_unpack<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        let mut file_count: usize = 0;
+        let mut total_bytes: u64 = 0;
+        for entry in file_system::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(n) => n,
+                None => {
+                    return Err(Error::UnsafeSnapshotEntry(format!(
+                        "non-utf8 entry in {}",
+                        dir.display()
+                    )));
+                }
+            };
+            // Reject any path that tries to escape the target directory.
+            for comp in Path::new(name).components() {
+                match comp {
+                    Component::Normal(_) => {}
+                    _ => {
+                        return Err(Error::UnsafeSnapshotEntry(format!(
+                            "entry {} contains an illegal path component",
+                            name
+                        )));
+                    }
+                }
+            }
+            if is_symlink(&path)? {
+                return Err(Error::UnsafeSnapshotEntry(format!(
+                    "entry {} is a symlink",
+                    name
+                )));
+            }
+            let meta = box_try!(std::fs::symlink_metadata(&path));
+            if !meta.is_file() {
+                return Err(Error::UnsafeSnapshotEntry(format!(
+                    "entry {} is not a regular file",
+                    name
+                )));
+            }
+            file_count += 1;
+            total_bytes = total_bytes.saturating_add(meta.len());
+            if self.max_recv_file_count > 0 && file_count > self.max_recv_file_count {
+                return Err(Error::SnapshotTooLarge(format!(
+                    "received {} files exceeds cap {}",
+                    file_count, self.max_recv_file_count
+                )));
+            }
+            if self.max_recv_total_bytes > 0 && total_bytes > self.max_recv_total_bytes {
+                return Err(Error::SnapshotTooLarge(format!(
+                    "received {} bytes exceeds cap {}",
+                    total_bytes, self.max_recv_total_bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub fn start_receive(&self, key: TabletSnapKey) -> Option<ReceivingGuard<'_>> {
         let mut receiving = self.receiving.lock().unwrap();
         if receiving.iter().any(|k| k == &key) {
@@ -2585,6 +4752,22 @@ pub mod tests {
             encryption_key_manager: None,
             max_per_file_size: Arc::new(AtomicU64::new(max_per_file_size)),
             enable_multi_snapshot_files: Arc::new(AtomicBool::new(true)),
+            build_concurrency: Arc::new(AtomicUsize::new(1)),
+            max_build_concurrency: 1,
+            compression_policy: Default::default(),
+            archive_format: Default::default(),
+            archive_compression_level: 0,
+            enable_streaming_apply: false,
+            base_dependents: Default::default(),
+            full_sends: Arc::default(),
+            incremental_sends: Arc::default(),
+            snap_io_concurrency: 1,
+            max_snapshots_per_region: 0,
+            max_snapshots_to_retain: 0,
+            max_recv_snap_size: 0,
+            max_recv_file_count: 0,
+            verify_apply_content_hash: false,
+            verify_apply_fs_security: None,
             max_total_size: Arc::new(AtomicU64::new(u64::MAX)),
             stats: Default::default(),
             min_ingest_cf_size: 0,
@@ -2644,7 +4827,7 @@ pub mod tests {
             };
             cf_file.push(f);
         }
-        let meta = super::gen_snapshot_meta(&cf_file, false).unwrap();
+        let (meta, _meta_ext) = super::gen_snapshot_meta(&cf_file, None, false).unwrap();
         let cf_files = meta.get_cf_files();
         assert_eq!(cf_files.len(), super::SNAPSHOT_CFS.len() * 2); // each CF has two snapshot files;
         for (i, cf_file_meta) in meta.get_cf_files().iter().enumerate() {
@@ -2744,9 +4927,15 @@ pub mod tests {
         // TODO check meta data correct.
         let _ = s2.meta().unwrap();
 
-        let mut s3 =
-            Snapshot::new_for_receiving(src_dir.path(), &key, &mgr_core, snap_data.take_meta())
-                .unwrap();
+        let meta_ext = s2.snapshot_meta_ext().clone();
+        let mut s3 = Snapshot::new_for_receiving(
+            src_dir.path(),
+            &key,
+            &mgr_core,
+            snap_data.take_meta(),
+            meta_ext,
+        )
+        .unwrap();
         assert!(!s3.exists());
 
         // Ensure snapshot data could be read out of `s2`, and write into `s3`.
@@ -2784,6 +4973,7 @@ pub mod tests {
             write_batch_size: TEST_WRITE_BATCH_SIZE,
             coprocessor_host: CoprocessorHost::<KvTestEngine>::default(),
             ingest_copy_symlink: false,
+            apply_concurrency: 1,
         };
         // Verify the snapshot applying is ok.
         s4.apply(options).unwrap();
@@ -2933,8 +5123,10 @@ pub mod tests {
     ) {
         let mut from = Snapshot::new_for_sending(from_dir.path(), key, mgr).unwrap();
         assert!(from.exists());
+        let meta_ext = from.snapshot_meta_ext().clone();
 
-        let mut to = Snapshot::new_for_receiving(to_dir.path(), key, mgr, snapshot_meta).unwrap();
+        let mut to =
+            Snapshot::new_for_receiving(to_dir.path(), key, mgr, snapshot_meta, meta_ext).unwrap();
 
         assert!(!to.exists());
         let _ = io::copy(&mut from, &mut to).unwrap();
@@ -2997,10 +5189,59 @@ pub mod tests {
             write_batch_size: TEST_WRITE_BATCH_SIZE,
             coprocessor_host: CoprocessorHost::<KvTestEngine>::default(),
             ingest_copy_symlink: false,
+            apply_concurrency: 1,
         };
         s2.apply(options).unwrap_err();
     }
 
+    #[test]
+    fn test_snap_save_fault_leaves_tmp_files() {
+        let region_id = 1;
+        let region = gen_test_region(region_id, 1, 1);
+        let db_dir = Builder::new()
+            .prefix("test-snap-fault-db")
+            .tempdir()
+            .unwrap();
+        let db: KvTestEngine = open_test_db_with_100keys(db_dir.path(), None, None).unwrap();
+        let snapshot = db.snapshot();
+
+        let dir = Builder::new().prefix("test-snap-fault").tempdir().unwrap();
+        let key = SnapKey::new(region_id, 1, 1);
+        let mgr_core = create_manager_core(dir.path().to_str().unwrap(), u64::MAX);
+        let mut s1 = Snapshot::new_for_building(dir.path(), &key, &mgr_core).unwrap();
+        let snap_data = s1
+            .build(&db, &snapshot, &region, true, false, UnixSecs::now())
+            .unwrap();
+        assert!(s1.exists());
+
+        let dst_dir = Builder::new()
+            .prefix("test-snap-fault-dst")
+            .tempdir()
+            .unwrap();
+        let mut from = Snapshot::new_for_sending(dir.path(), &key, &mgr_core).unwrap();
+        let meta_ext = from.snapshot_meta_ext().clone();
+        let mut to = Snapshot::new_for_receiving(
+            dst_dir.path(),
+            &key,
+            &mgr_core,
+            snap_data.get_meta().clone(),
+            meta_ext,
+        )
+        .unwrap();
+        let _ = io::copy(&mut from, &mut to).unwrap();
+
+        // Fail the first rename, i.e. a crash after the tmp files are fully
+        // written but before they are moved into place. `save` must surface the
+        // error, the snapshot must not appear to exist, and the tmp files must
+        // still be held so `Drop` cleans them up.
+        fault::fail_nth(fault::FaultKind::Rename, 1, ErrorKind::Other);
+        to.save().unwrap_err();
+        fault::reset();
+
+        assert!(!to.exists());
+        assert!(to.hold_tmp_files);
+    }
+
     #[test]
     fn test_snap_corruption_on_meta_file() {
         let region_id = 1;
@@ -3052,8 +5293,15 @@ pub mod tests {
         assert_eq!(1, corrupt_snapshot_meta_file(dst_dir.path()));
 
         Snapshot::new_for_applying(dst_dir.path(), &key, &mgr_core).unwrap_err();
-        Snapshot::new_for_receiving(dst_dir.path(), &key, &mgr_core, snap_data.take_meta())
-            .unwrap_err();
+        let meta_ext = s2.snapshot_meta_ext().clone();
+        Snapshot::new_for_receiving(
+            dst_dir.path(),
+            &key,
+            &mgr_core,
+            snap_data.take_meta(),
+            meta_ext,
+        )
+        .unwrap_err();
     }
 
     #[test]
@@ -3101,9 +5349,15 @@ pub mod tests {
             .unwrap();
         let mut s = Snapshot::new_for_sending(&path, &key1, &mgr_core).unwrap();
         let expected_size = s.total_size();
-        let mut s2 =
-            Snapshot::new_for_receiving(&path, &key1, &mgr_core, snap_data.get_meta().clone())
-                .unwrap();
+        let meta_ext = s.snapshot_meta_ext().clone();
+        let mut s2 = Snapshot::new_for_receiving(
+            &path,
+            &key1,
+            &mgr_core,
+            snap_data.get_meta().clone(),
+            meta_ext.clone(),
+        )
+        .unwrap();
         let n = io::copy(&mut s, &mut s2).unwrap();
         assert_eq!(n, expected_size);
         s2.save().unwrap();
@@ -3112,8 +5366,14 @@ pub mod tests {
         region.set_id(2);
         snap_data.set_region(region);
         let s3 = Snapshot::new_for_building(&path, &key2, &mgr_core).unwrap();
-        let s4 =
-            Snapshot::new_for_receiving(&path, &key2, &mgr_core, snap_data.take_meta()).unwrap();
+        let s4 = Snapshot::new_for_receiving(
+            &path,
+            &key2,
+            &mgr_core,
+            snap_data.take_meta(),
+            meta_ext,
+        )
+        .unwrap();
 
         assert!(s1.exists());
         assert!(s2.exists());
@@ -3135,6 +5395,45 @@ pub mod tests {
         assert_eq!(mgr.get_total_snap_size().unwrap(), 0);
     }
 
+    #[test]
+    fn test_verify_all_snapshots() {
+        let temp_dir = Builder::new()
+            .prefix("test-verify-all-snapshots")
+            .tempdir()
+            .unwrap();
+        let path = temp_dir.path().to_str().unwrap().to_owned();
+
+        let db_dir = Builder::new()
+            .prefix("test-verify-all-snapshots-db")
+            .tempdir()
+            .unwrap();
+        let db: KvTestEngine = open_test_db(db_dir.path(), None, None).unwrap();
+        let snapshot = db.snapshot();
+        let key = SnapKey::new(1, 1, 1);
+        let mgr_core = create_manager_core(&path, u64::MAX);
+        let mut s1 = Snapshot::new_for_building(&path, &key, &mgr_core).unwrap();
+        let region = gen_test_region(1, 1, 1);
+        let mut snap_data = s1
+            .build(&db, &snapshot, &region, true, false, UnixSecs::now())
+            .unwrap();
+        let mut s = Snapshot::new_for_sending(&path, &key, &mgr_core).unwrap();
+        let meta_ext = s.snapshot_meta_ext().clone();
+        let mut s2 =
+            Snapshot::new_for_receiving(&path, &key, &mgr_core, snap_data.take_meta(), meta_ext)
+                .unwrap();
+        io::copy(&mut s, &mut s2).unwrap();
+        s2.save().unwrap();
+
+        let mgr = SnapManager::new(path);
+        mgr.init().unwrap();
+
+        mgr.verify_snapshot(&key).unwrap();
+        let results = mgr.verify_all_snapshots().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, key);
+        results[0].1.as_ref().unwrap();
+    }
+
     fn check_registry_around_deregister(mgr: &SnapManager, key: &SnapKey, entry: &SnapEntry) {
         let snap_keys = mgr.list_idle_snap().unwrap();
         assert!(snap_keys.is_empty());
@@ -3191,8 +5490,9 @@ pub mod tests {
 
         // Ensure the snapshot being received will not be deleted on GC.
         dst_mgr.register(key.clone(), SnapEntry::Receiving);
+        let meta_ext = s2.snapshot_meta_ext().clone();
         let mut s3 = dst_mgr
-            .get_snapshot_for_receiving(&key, snap_data.take_meta())
+            .get_snapshot_for_receiving(&key, snap_data.take_meta(), meta_ext)
             .unwrap();
         let n = io::copy(&mut s2, &mut s3).unwrap();
         assert_eq!(n, expected_size);
@@ -3260,15 +5560,16 @@ pub mod tests {
             )
             .unwrap()
         };
-        let recv_remain = {
+        let (recv_remain, recv_meta_ext) = {
             let mut data = Vec::with_capacity(1024);
             let mut s = snap_mgr.get_snapshot_for_sending(&recv_key).unwrap();
             s.read_to_end(&mut data).unwrap();
+            let meta_ext = s.snapshot_meta_ext().clone();
             assert!(snap_mgr.delete_snapshot(&recv_key, s.as_ref(), true));
-            data
+            (data, meta_ext)
         };
         let mut s = snap_mgr
-            .get_snapshot_for_receiving(&recv_key, recv_head.take_meta())
+            .get_snapshot_for_receiving(&recv_key, recv_head.take_meta(), recv_meta_ext)
             .unwrap();
         s.write_all(&recv_remain).unwrap();
         s.save().unwrap();
@@ -3349,6 +5650,244 @@ pub mod tests {
         assert!(!path.exists());
     }
 
+    #[test]
+    fn test_gc_gen_checkpoints() {
+        let snap_dir = Builder::new()
+            .prefix("test_gc_gen_checkpoints")
+            .tempdir()
+            .unwrap();
+        let mut mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+        // Keep at most the two most-recent checkpoints per region.
+        mgr.set_gen_checkpoint_retention(2, time::Duration::ZERO);
+
+        // Three checkpoints for region 1 and one for region 2.
+        let keys = [
+            TabletSnapKey::new(1, 1, 1, 10),
+            TabletSnapKey::new(1, 1, 1, 20),
+            TabletSnapKey::new(1, 1, 1, 30),
+            TabletSnapKey::new(2, 1, 1, 5),
+        ];
+        for key in &keys {
+            std::fs::create_dir_all(mgr.tablet_gen_path(key)).unwrap();
+        }
+
+        let purged = mgr.gc_gen_checkpoints().unwrap();
+        // Only the oldest checkpoint of region 1 is dropped; region 2 is within
+        // the cap.
+        assert_eq!(purged, vec![TabletSnapKey::new(1, 1, 1, 10)]);
+        assert!(!mgr.tablet_gen_path(&keys[0]).exists());
+        assert!(mgr.tablet_gen_path(&keys[1]).exists());
+        assert!(mgr.tablet_gen_path(&keys[2]).exists());
+        assert!(mgr.tablet_gen_path(&keys[3]).exists());
+
+        // A zero TTL with a zero count cap is a no-op.
+        mgr.set_gen_checkpoint_retention(0, time::Duration::ZERO);
+        assert!(mgr.gc_gen_checkpoints().unwrap().is_empty());
+
+        // An aggressive TTL trashes every remaining checkpoint regardless of
+        // count.
+        mgr.set_gen_checkpoint_retention(0, time::Duration::from_nanos(1));
+        let mut purged = mgr.gc_gen_checkpoints().unwrap();
+        purged.sort();
+        assert_eq!(
+            purged,
+            vec![
+                TabletSnapKey::new(1, 1, 1, 20),
+                TabletSnapKey::new(1, 1, 1, 30),
+                TabletSnapKey::new(2, 1, 1, 5),
+            ]
+        );
+        assert!(mgr.list_snapshot().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_spawn_checkpoint_gc() {
+        let snap_dir = Builder::new()
+            .prefix("test_spawn_checkpoint_gc")
+            .tempdir()
+            .unwrap();
+        let mut mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+        mgr.set_gen_checkpoint_retention(1, time::Duration::ZERO);
+
+        let keys = [
+            TabletSnapKey::new(1, 1, 1, 10),
+            TabletSnapKey::new(1, 1, 1, 20),
+        ];
+        for key in &keys {
+            std::fs::create_dir_all(mgr.tablet_gen_path(key)).unwrap();
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = mgr
+            .spawn_checkpoint_gc(time::Duration::from_millis(5), stop.clone())
+            .unwrap();
+
+        // The background loop purges the older checkpoint without anything
+        // else driving it.
+        for _ in 0..200 {
+            if !mgr.tablet_gen_path(&keys[0]).exists() {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(5));
+        }
+        assert!(!mgr.tablet_gen_path(&keys[0]).exists());
+        assert!(mgr.tablet_gen_path(&keys[1]).exists());
+
+        // The loop's periodic inventory log reads through
+        // `all_tablet_snapshots`, which should agree with what's left on disk
+        // after GC.
+        assert_eq!(mgr.all_tablet_snapshots().unwrap(), vec![keys[1].clone()]);
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_verify_received_manifest() {
+        let snap_dir = Builder::new()
+            .prefix("test_verify_received_manifest")
+            .tempdir()
+            .unwrap();
+        let mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+        let key = TabletSnapKey::new(1, 1, 1, 1);
+        let dir = mgr.final_recv_path(&key);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("default.sst"), b"default-cf-bytes").unwrap();
+        std::fs::write(dir.join("write.sst"), b"write-cf-bytes").unwrap();
+
+        mgr.write_manifest(&dir, &key).unwrap();
+        mgr.verify_received(&key).unwrap();
+
+        // A corrupted file is caught and named.
+        std::fs::write(dir.join("write.sst"), b"tampered").unwrap();
+        let err = mgr.verify_received(&key).unwrap_err();
+        assert!(
+            matches!(&err, Error::ContentHashMismatch(msg) if msg.contains("write.sst")),
+            "unexpected error: {:?}",
+            err
+        );
+
+        // A dropped manifest is reported distinctly.
+        std::fs::remove_file(dir.join(TABLET_SNAP_MANIFEST)).unwrap();
+        assert!(matches!(
+            mgr.verify_received(&key).unwrap_err(),
+            Error::ContentHashMismatch(_)
+        ));
+    }
+
+    #[test]
+    fn test_tablet_snapshot_archive_round_trip() {
+        let snap_dir = Builder::new()
+            .prefix("test_tablet_snapshot_archive_round_trip")
+            .tempdir()
+            .unwrap();
+        let mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+
+        let key = TabletSnapKey::new(1, 2, 3, 4);
+        assert_eq!(key.to_archive_name(), "gen_1_2_3_4.tsnap");
+        assert_eq!(
+            TabletSnapKey::from_archive_name(&key.to_archive_name()).unwrap(),
+            key
+        );
+
+        let gen_dir = mgr.tablet_gen_path(&key);
+        std::fs::create_dir_all(&gen_dir).unwrap();
+        std::fs::write(gen_dir.join("default.sst"), b"default-bytes").unwrap();
+        std::fs::write(gen_dir.join("write.sst"), b"write-bytes").unwrap();
+
+        let archive = mgr.pack_snapshot(&key).unwrap();
+        assert!(archive.exists());
+
+        mgr.unpack_snapshot(&key, &archive).unwrap();
+        let recv_dir = mgr.final_recv_path(&key);
+        assert_eq!(
+            std::fs::read(recv_dir.join("default.sst")).unwrap(),
+            b"default-bytes"
+        );
+        assert_eq!(
+            std::fs::read(recv_dir.join("write.sst")).unwrap(),
+            b"write-bytes"
+        );
+        // The staging dir was renamed into place, not left behind.
+        assert!(!mgr.tmp_recv_path(&key).exists());
+    }
+
+    #[test]
+    fn test_finish_receiving_hardens_directory_mode_receive() {
+        let snap_dir = Builder::new()
+            .prefix("test_finish_receiving_hardens_directory_mode_receive")
+            .tempdir()
+            .unwrap();
+        let mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+
+        let key = TabletSnapKey::new(1, 2, 3, 4);
+        let tmp = mgr.tmp_recv_path(&key);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("default.sst"), b"default-bytes").unwrap();
+
+        mgr.finish_receiving(&key).unwrap();
+        let recv_dir = mgr.final_recv_path(&key);
+        assert_eq!(
+            std::fs::read(recv_dir.join("default.sst")).unwrap(),
+            b"default-bytes"
+        );
+        assert!(!mgr.tmp_recv_path(&key).exists());
+    }
+
+    #[test]
+    fn test_finish_receiving_enforces_recv_budget() {
+        let snap_dir = Builder::new()
+            .prefix("test_finish_receiving_enforces_recv_budget")
+            .tempdir()
+            .unwrap();
+        let mut mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+        mgr.set_recv_limits(0, 1);
+
+        let key = TabletSnapKey::new(1, 2, 3, 4);
+        let tmp = mgr.tmp_recv_path(&key);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("default.sst"), b"default-bytes").unwrap();
+
+        // A per-file receive that exceeds the total-bytes cap set through
+        // `set_recv_limits` is rejected before promotion, the same as the archive-mode
+        // receive path. The staging directory is dropped, not landed.
+        assert!(matches!(
+            mgr.finish_receiving(&key).unwrap_err(),
+            Error::SnapshotTooLarge(_)
+        ));
+        assert!(!mgr.final_recv_path(&key).exists());
+        assert!(!tmp.exists());
+    }
+
+    #[test]
+    fn test_all_tablet_snapshots_skip_locked() {
+        let snap_dir = Builder::new()
+            .prefix("test_all_tablet_snapshots_skip_locked")
+            .tempdir()
+            .unwrap();
+        let mgr = TabletSnapManager::new(snap_dir.path(), None).unwrap();
+
+        let ready = TabletSnapKey::new(1, 1, 1, 1);
+        let building = TabletSnapKey::new(2, 1, 1, 1);
+        let ready_dir = mgr.tablet_gen_path(&ready);
+        let building_dir = mgr.tablet_gen_path(&building);
+        std::fs::create_dir_all(&ready_dir).unwrap();
+        std::fs::create_dir_all(&building_dir).unwrap();
+        // The second snapshot is still being materialized.
+        mgr.lock_snapshot(&building_dir).unwrap();
+
+        let keys = mgr.all_tablet_snapshots().unwrap();
+        assert_eq!(keys, vec![ready.clone()]);
+        // A locked snapshot's key is treated as nonexistent.
+        assert!(TabletSnapKey::from_path(&building_dir).is_err());
+
+        // Once published, it becomes visible.
+        mgr.unlock_snapshot(&building_dir).unwrap();
+        let mut keys = mgr.all_tablet_snapshots().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec![ready, building]);
+    }
+
     #[test]
     fn test_build_with_encryption() {
         let (_enc_dir, key_manager) =
@@ -1,11 +1,12 @@
 // Copyright 2019 TiKV Project Authors. Licensed under Apache-2.0.
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fs,
     fs::{File, OpenOptions},
     io::{self, BufReader, Read, Write},
     sync::Arc,
-    usize,
+    thread, usize,
 };
 
 use encryption::{
@@ -23,6 +24,7 @@ use tikv_util::{
     codec::bytes::{BytesEncoder, CompactBytesFromFileDecoder},
     debug, error, info,
     time::{Instant, Limiter},
+    warn,
 };
 
 use super::{CfFile, Error, IO_LIMITER_CHUNK_SIZE};
@@ -38,12 +40,269 @@ pub struct BuildStatistics {
     pub total_size: usize,
 }
 
+/// Compression settings for a single column family's SST files.
+///
+/// `codec == None` disables block compression entirely, which suits the small,
+/// write-heavy lock/write CFs that compress poorly; large data CFs instead pick
+/// a high-ratio `Zstd` codec. `zstd_level` overrides the engine default when the
+/// codec is `Zstd`, and `dict_sample_bytes`, when non-zero, enables training a
+/// shared Zstd dictionary from the first that-many bytes of scanned values and
+/// reusing it across every SST file produced for the CF.
+#[derive(Clone, Copy)]
+pub struct CfCompression {
+    pub codec: Option<SstCompressionType>,
+    pub zstd_level: Option<i32>,
+    pub dict_sample_bytes: usize,
+}
+
+impl Default for CfCompression {
+    fn default() -> Self {
+        // Preserve the historical behaviour: every CF is Zstd-compressed with
+        // the engine default level and no trained dictionary.
+        CfCompression {
+            codec: Some(SstCompressionType::Zstd),
+            zstd_level: None,
+            dict_sample_bytes: 0,
+        }
+    }
+}
+
+/// A per-CF compression policy: a default plus optional overrides keyed by CF
+/// name, so large data CFs and cheap lock/write CFs can use different codecs.
+#[derive(Clone, Default)]
+pub struct SstCompressionPolicy {
+    default: CfCompression,
+    per_cf: HashMap<CfName, CfCompression>,
+}
+
+impl SstCompressionPolicy {
+    pub fn new(default: CfCompression) -> Self {
+        SstCompressionPolicy {
+            default,
+            per_cf: HashMap::new(),
+        }
+    }
+
+    pub fn set_cf(&mut self, cf: CfName, cc: CfCompression) {
+        self.per_cf.insert(cf, cc);
+    }
+
+    pub fn resolve(&self, cf: CfName) -> CfCompression {
+        self.per_cf.get(cf).copied().unwrap_or(self.default)
+    }
+}
+
+/// Wire archive format applied to a CF file *before* encryption, so the
+/// existing AES-CTR crypter in the receiving path still operates on opaque
+/// bytes. Receivers read the format from the snapshot meta to pick the matching
+/// decoder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+    None,
+    Zstd,
+    Gzip,
+    Lz4,
+}
+
+impl Default for ArchiveFormat {
+    fn default() -> Self {
+        ArchiveFormat::None
+    }
+}
+
+/// Operator-facing selection of the snapshot CF-file compression codec. Unlike
+/// [`ArchiveFormat`], which is the on-wire tag, this carries the Zstd effort
+/// level so it can be configured as a single option and lowered into an
+/// `(ArchiveFormat, level)` pair by [`CompressionFormat::into_parts`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CompressionFormat {
+    None,
+    Lz4,
+    Zstd { level: i32 },
+}
+
+impl Default for CompressionFormat {
+    fn default() -> Self {
+        CompressionFormat::None
+    }
+}
+
+impl CompressionFormat {
+    /// Lower into the wire format tag and compression effort level consumed by
+    /// the send/receive paths.
+    pub fn into_parts(self) -> (ArchiveFormat, i32) {
+        match self {
+            CompressionFormat::None => (ArchiveFormat::None, 0),
+            CompressionFormat::Lz4 => (ArchiveFormat::Lz4, 0),
+            CompressionFormat::Zstd { level } => (ArchiveFormat::Zstd, level),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    /// Stable wire tag recorded in the snapshot meta.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            ArchiveFormat::None => 0,
+            ArchiveFormat::Zstd => 1,
+            ArchiveFormat::Gzip => 2,
+            ArchiveFormat::Lz4 => 3,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> ArchiveFormat {
+        match v {
+            1 => ArchiveFormat::Zstd,
+            2 => ArchiveFormat::Gzip,
+            3 => ArchiveFormat::Lz4,
+            _ => ArchiveFormat::None,
+        }
+    }
+
+    /// Wrap `inner` in the matching streaming compressor. `level` selects the
+    /// compression effort; `0` asks each codec for its own default so callers
+    /// that don't care can pass `0`.
+    fn writer<W: Write + 'static>(
+        self,
+        inner: W,
+        level: i32,
+    ) -> Result<ArchiveWriter<W>, Error> {
+        Ok(match self {
+            ArchiveFormat::None => ArchiveWriter::Plain(inner),
+            ArchiveFormat::Zstd => ArchiveWriter::Zstd(box_try!(zstd::Encoder::new(inner, level))),
+            ArchiveFormat::Gzip => {
+                let compression = if level <= 0 {
+                    flate2::Compression::default()
+                } else {
+                    flate2::Compression::new((level as u32).min(9))
+                };
+                ArchiveWriter::Gzip(flate2::write::GzEncoder::new(inner, compression))
+            }
+            ArchiveFormat::Lz4 => {
+                let mut builder = lz4::EncoderBuilder::new();
+                if level > 0 {
+                    builder.level(level as u32);
+                }
+                ArchiveWriter::Lz4(box_try!(builder.build(inner)))
+            }
+        })
+    }
+
+    /// Wrap `inner` in the matching streaming decompressor.
+    pub fn reader<R: Read + 'static>(self, inner: R) -> Result<Box<dyn Read>, Error> {
+        Ok(match self {
+            ArchiveFormat::None => Box::new(inner),
+            ArchiveFormat::Zstd => Box::new(box_try!(zstd::Decoder::new(inner))),
+            ArchiveFormat::Gzip => Box::new(flate2::read::GzDecoder::new(inner)),
+            ArchiveFormat::Lz4 => Box::new(box_try!(lz4::Decoder::new(inner))),
+        })
+    }
+}
+
+/// A streaming compressor selected by [`ArchiveFormat`]. `finish` drains any
+/// buffered output and returns the underlying writer so the caller can sync it.
+enum ArchiveWriter<W: Write> {
+    Plain(W),
+    Zstd(zstd::Encoder<'static, W>),
+    Gzip(flate2::write::GzEncoder<W>),
+    Lz4(lz4::Encoder<W>),
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    fn finish(self) -> Result<W, Error> {
+        Ok(match self {
+            ArchiveWriter::Plain(w) => w,
+            ArchiveWriter::Zstd(e) => box_try!(e.finish()),
+            ArchiveWriter::Gzip(e) => box_try!(e.finish()),
+            ArchiveWriter::Lz4(e) => {
+                let (w, res) = e.finish();
+                box_try!(res);
+                w
+            }
+        })
+    }
+}
+
+impl<W: Write> Write for ArchiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ArchiveWriter::Plain(w) => w.write(buf),
+            ArchiveWriter::Zstd(e) => e.write(buf),
+            ArchiveWriter::Gzip(e) => e.write(buf),
+            ArchiveWriter::Lz4(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ArchiveWriter::Plain(w) => w.flush(),
+            ArchiveWriter::Zstd(e) => e.flush(),
+            ArchiveWriter::Gzip(e) => e.flush(),
+            ArchiveWriter::Lz4(e) => e.flush(),
+        }
+    }
+}
+
+/// The encryption sink a plain CF file is written to. Compression always sits
+/// *above* this sink so the on-disk bytes are `encrypt(compress(logical))` and
+/// the existing CTR crypter keeps operating on opaque input.
+enum PlainSink {
+    Plain(File),
+    Encrypted(EncrypterWriter<File>),
+}
+
+impl Write for PlainSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            PlainSink::Plain(f) => f.write(buf),
+            PlainSink::Encrypted(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            PlainSink::Plain(f) => f.flush(),
+            PlainSink::Encrypted(e) => e.flush(),
+        }
+    }
+}
+
+impl PlainSink {
+    fn into_file(self) -> Result<File, Error> {
+        Ok(match self {
+            PlainSink::Plain(f) => f,
+            PlainSink::Encrypted(e) => box_try!(e.finalize()),
+        })
+    }
+}
+
+// Neither this function nor `build_sst_cf_file_list` below chunk their output
+// with content-defined boundaries for cross-snapshot deduplication. That was
+// tried (see the `chunk` module's history): a FastCDC gear-hash chunk store
+// with a SHA-256 dedup index was built, but it was never hooked up here,
+// because doing so isn't just a write-side change — `apply_plain_cf_file`/
+// `apply_sst_cf_files_*` below would need a matching reader for the chunked
+// wire format, and the format change needs a version flag so a mid-rollout
+// mix of old and new receivers doesn't misparse each other's snapshots. That
+// is a real feature, not a drop-in helper, so it was reverted rather than
+// left half-wired behind this call. Revisit by adding a manifest format
+// version to `CfFile`/the snapshot meta, writing `ChunkRef` lists only when
+// the receiver advertises support for it, and falling back to the plain
+// stream otherwise.
+//
 /// Build a snapshot file for the given column family in plain format.
 /// If there are no key-value pairs fetched, no files will be created at `path`,
 /// otherwise the file will be created and synchronized.
+///
+/// When `archive` selects a compressor the logical key/value stream is
+/// compressed before encryption, shrinking WAN transfer for cold regions. The
+/// logical (decrypted, decompressed) crc32 trailer is still written so the
+/// apply side validates content integrity after decoding.
 pub fn build_plain_cf_file<E>(
     cf_file: &mut CfFile,
     key_mgr: Option<&Arc<DataKeyManager>>,
+    archive: ArchiveFormat,
+    archive_level: i32,
     snap: &E::Snapshot,
     start_key: &[u8],
     end_key: &[u8],
@@ -52,6 +311,7 @@ where
     E: KvEngine,
 {
     let cf = cf_file.cf;
+    cf_file.archive_format = archive;
     let path = cf_file.path.join(cf_file.gen_tmp_file_name(0));
     let path = path.to_str().unwrap();
     let mut file = Some(box_try!(
@@ -75,32 +335,45 @@ where
         }
     }
 
-    let mut writer = if !should_encrypt {
-        file.as_mut().unwrap() as &mut dyn Write
+    let sink = if !should_encrypt {
+        PlainSink::Plain(file.take().unwrap())
     } else {
-        encrypted_file.as_mut().unwrap() as &mut dyn Write
+        PlainSink::Encrypted(encrypted_file.take().unwrap())
     };
+    let mut writer = box_try!(archive.writer(sink, archive_level));
 
     let mut stats = BuildStatistics::default();
+    // A whole-file checksum over the logical (decrypted) key/value bytes. The
+    // SST path already detects corruption via `verify_checksum`; the plain path
+    // had no such guard, so a flipped bit in a CTR-encrypted (unauthenticated)
+    // plain file used to be written into the DB silently. We persist this crc
+    // as a trailer and verify it on apply.
+    let mut digest = crc32fast::Hasher::new();
     box_try!(snap.scan(cf, start_key, end_key, false, |key, value| {
         stats.key_count += 1;
         stats.total_size += key.len() + value.len();
+        digest.update(key);
+        digest.update(value);
         box_try!(BytesEncoder::encode_compact_bytes(&mut writer, key));
         box_try!(BytesEncoder::encode_compact_bytes(&mut writer, value));
         Ok(true)
     }));
 
     if stats.key_count > 0 {
-        cf_file.add_file(0);
         box_try!(BytesEncoder::encode_compact_bytes(&mut writer, b""));
-        let file = if !should_encrypt {
-            file.unwrap()
-        } else {
-            encrypted_file.unwrap().finalize().unwrap()
-        };
+        // Trailer: the crc32 of the logical content, so readers can detect
+        // tampering/corruption without re-deriving it from ciphertext.
+        let checksum = digest.finalize();
+        box_try!(writer.write_all(&checksum.to_le_bytes()));
+        // Flush the compressor and hand the encrypter/file back so it can be
+        // synced. The final size/checksum are recomputed by the sending path
+        // once the file is moved into place (see `rename_tmp_cf_file_for_send`).
+        let sink = box_try!(writer.finish());
+        let file = sink.into_file()?;
         box_try!(file.sync_all());
+        cf_file.add_file_with_size_checksum(0, stats.total_size as u64, checksum);
     } else {
-        drop(file);
+        drop(writer);
         box_try!(fs::remove_file(path));
     }
 
@@ -108,8 +381,20 @@ where
 }
 
 /// Build a snapshot file for the given column family in sst format.
+///
+/// `concurrency` controls how many worker threads split the scan. With the
+/// default of `1` the range is scanned sequentially on the calling thread,
+/// preserving the historical behaviour exactly. With a larger value the
+/// `[start_key, end_key)` range is split into that many key-ordered subranges
+/// (see [`sample_split_keys`]), each scanned on its own worker thread with its
+/// own `SstWriter`; the resulting file lists are merged back into `cf_file` in
+/// key order. All workers share the single `io_limiter`, so IO throttling stays
+/// correct regardless of the worker count, and every emitted file still goes
+/// through the per-file `verify_checksum` step before being accepted.
+///
 /// If there are no key-value pairs fetched, no files will be created at `path`,
 /// otherwise the file will be created and synchronized.
+#[allow(clippy::too_many_arguments)]
 pub fn build_sst_cf_file_list<E>(
     cf_file: &mut CfFile,
     engine: &E,
@@ -119,11 +404,218 @@ pub fn build_sst_cf_file_list<E>(
     raw_size_per_file: u64,
     io_limiter: &Limiter,
     key_mgr: Option<Arc<DataKeyManager>>,
+    compression: &SstCompressionPolicy,
+    concurrency: usize,
 ) -> Result<BuildStatistics, Error>
 where
     E: KvEngine,
 {
     let cf = cf_file.cf;
+    let cc = compression.resolve(cf);
+    // Record the chosen codec so the apply side and diagnostics can observe it.
+    cf_file.compression = cc.codec;
+    // Train one shared dictionary up front (if requested) and reuse it across
+    // every file the CF produces, including across parallel workers.
+    let dict = train_cf_dictionary::<E>(snap, cf, start_key, end_key, &cc)?;
+
+    if concurrency <= 1 {
+        return scan_cf_to_sst_files::<E>(
+            cf_file,
+            engine,
+            snap,
+            start_key,
+            end_key,
+            raw_size_per_file,
+            io_limiter,
+            key_mgr,
+            cc,
+            dict,
+        );
+    }
+
+    let subranges = sample_split_keys::<E>(snap, cf, start_key, end_key, concurrency)?;
+    let dir = cf_file.path.clone();
+    let prefix = cf_file.file_prefix.clone();
+    let suffix = cf_file.file_suffix.clone();
+
+    // Each worker writes into its own scratch `CfFile` (distinct file prefix) so
+    // the concurrently-created temp files never collide, then we merge the
+    // scratch file lists back in key order below.
+    let parts: Vec<(CfFile, Result<BuildStatistics, Error>)> = thread::scope(|s| {
+        let handles: Vec<_> = subranges
+            .iter()
+            .enumerate()
+            .map(|(idx, (sub_start, sub_end))| {
+                let mut scratch = CfFile::new(
+                    cf,
+                    dir.clone(),
+                    format!("{}_p{:02}", prefix, idx),
+                    suffix.clone(),
+                );
+                let key_mgr = key_mgr.clone();
+                let dict = dict.clone();
+                s.spawn(move || {
+                    let stat = scan_cf_to_sst_files::<E>(
+                        &mut scratch,
+                        engine,
+                        snap,
+                        sub_start,
+                        sub_end,
+                        raw_size_per_file,
+                        io_limiter,
+                        key_mgr,
+                        cc,
+                        dict,
+                    );
+                    (scratch, stat)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut stats = BuildStatistics::default();
+    let mut file_id: usize = 0;
+    for (scratch, stat) in parts {
+        let stat = stat?;
+        stats.key_count += stat.key_count;
+        stats.total_size += stat.total_size;
+        // Rename each scratch temp file to its canonical position so the final
+        // `cf_file` carries a contiguous, key-ordered file list.
+        for src in scratch.tmp_file_paths() {
+            let dst = cf_file
+                .path
+                .join(cf_file.gen_tmp_file_name(file_id))
+                .to_str()
+                .unwrap()
+                .to_string();
+            rename_cf_tmp_file(&src, &dst, key_mgr.as_ref())?;
+            cf_file.add_file(file_id);
+            file_id += 1;
+        }
+    }
+    Ok(stats)
+}
+
+/// Scan `[start_key, end_key)` once and collect an evenly-spaced set of internal
+/// boundary keys so the range can be split into `parts` key-ordered subranges.
+/// The returned subranges always cover the whole range: there are `parts` of
+/// them when the range holds at least `parts` keys, and a single subrange when
+/// it holds fewer keys than that (or none, or `parts <= 1`). Keys are sampled by key count, which keeps each worker's byte
+/// volume roughly balanced without depending on engine-specific split APIs.
+fn sample_split_keys<E>(
+    snap: &E::Snapshot,
+    cf: CfName,
+    start_key: &[u8],
+    end_key: &[u8],
+    parts: usize,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error>
+where
+    E: KvEngine,
+{
+    let mut total = 0usize;
+    box_try!(snap.scan(cf, start_key, end_key, false, |_, _| {
+        total += 1;
+        Ok(true)
+    }));
+    if total == 0 || parts <= 1 {
+        return Ok(vec![(start_key.to_vec(), end_key.to_vec())]);
+    }
+    // Pick boundary keys at the `i * (total / parts)`-th key for i in 1..parts.
+    let step = total / parts;
+    let mut bounds: Vec<Vec<u8>> = Vec::with_capacity(parts - 1);
+    let mut seen = 0usize;
+    box_try!(snap.scan(cf, start_key, end_key, false, |key, _| {
+        seen += 1;
+        if step > 0 && bounds.len() < parts - 1 && seen == (bounds.len() + 1) * step {
+            bounds.push(key.to_vec());
+        }
+        Ok(true)
+    }));
+
+    let mut subranges = Vec::with_capacity(parts);
+    let mut lower = start_key.to_vec();
+    for b in bounds {
+        subranges.push((lower.clone(), b.clone()));
+        lower = b;
+    }
+    subranges.push((lower, end_key.to_vec()));
+    Ok(subranges)
+}
+
+/// Move a scratch temp SST file to its canonical temp path, keeping the
+/// encryption key-manager metadata in sync when encryption is enabled.
+fn rename_cf_tmp_file(
+    src: &str,
+    dst: &str,
+    key_mgr: Option<&Arc<DataKeyManager>>,
+) -> Result<(), Error> {
+    if let Some(mgr) = key_mgr {
+        box_try!(mgr.link_file(src, dst));
+    }
+    box_try!(fs::rename(src, dst));
+    if let Some(mgr) = key_mgr {
+        box_try!(mgr.delete_file(src, None));
+    }
+    Ok(())
+}
+
+/// Sample the first `dict_sample_bytes` of scanned values and train a Zstd
+/// dictionary from them. Returns `None` when dictionary training is disabled or
+/// the codec is not Zstd, or when too few samples were gathered to train a
+/// useful dictionary.
+fn train_cf_dictionary<E>(
+    snap: &E::Snapshot,
+    cf: CfName,
+    start_key: &[u8],
+    end_key: &[u8],
+    cc: &CfCompression,
+) -> Result<Option<Arc<Vec<u8>>>, Error>
+where
+    E: KvEngine,
+{
+    if cc.dict_sample_bytes == 0 || cc.codec != Some(SstCompressionType::Zstd) {
+        return Ok(None);
+    }
+    let mut samples: Vec<Vec<u8>> = Vec::new();
+    let mut sampled = 0usize;
+    box_try!(snap.scan(cf, start_key, end_key, false, |_, value| {
+        samples.push(value.to_vec());
+        sampled += value.len();
+        Ok(sampled < cc.dict_sample_bytes)
+    }));
+    // zstd needs a few samples to train; fall back to no dictionary otherwise.
+    if samples.len() < 8 {
+        return Ok(None);
+    }
+    let dict_size = (cc.dict_sample_bytes / 10).clamp(16 * 1024, 112 * 1024);
+    match zstd::dict::from_samples(&samples, dict_size) {
+        Ok(dict) => Ok(Some(Arc::new(dict))),
+        Err(e) => {
+            warn!("failed to train zstd dictionary, fall back to no dict"; "cf" => cf, "err" => ?e);
+            Ok(None)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn scan_cf_to_sst_files<E>(
+    cf_file: &mut CfFile,
+    engine: &E,
+    snap: &E::Snapshot,
+    start_key: &[u8],
+    end_key: &[u8],
+    raw_size_per_file: u64,
+    io_limiter: &Limiter,
+    key_mgr: Option<Arc<DataKeyManager>>,
+    cc: CfCompression,
+    dict: Option<Arc<Vec<u8>>>,
+) -> Result<BuildStatistics, Error>
+where
+    E: KvEngine,
+{
+    let cf = cf_file.cf;
+    let dict_bytes = dict.as_ref().map(|d| d.as_slice());
     let mut stats = BuildStatistics::default();
     let mut remained_quota = 0;
     let mut file_id: usize = 0;
@@ -133,7 +625,7 @@ where
         .to_str()
         .unwrap()
         .to_string();
-    let sst_writer = RefCell::new(create_sst_file_writer::<E>(engine, cf, &path)?);
+    let sst_writer = RefCell::new(create_sst_file_writer::<E>(engine, cf, &path, &cc, dict_bytes)?);
     let mut file_length: usize = 0;
 
     let finish_sst_writer = |sst_writer: E::SstWriter,
@@ -197,7 +689,7 @@ where
                 .to_str()
                 .unwrap()
                 .to_string();
-            let result = create_sst_file_writer::<E>(engine, cf, &path);
+            let result = create_sst_file_writer::<E>(engine, cf, &path, &cc, dict_bytes);
             match result {
                 Ok(new_sst_writer) => {
                     let old_writer = sst_writer.replace(new_sst_writer);
@@ -249,6 +741,7 @@ where
 pub fn apply_plain_cf_file<E, F>(
     path: &str,
     key_mgr: Option<&Arc<DataKeyManager>>,
+    archive: ArchiveFormat,
     stale_detector: &impl StaleDetector,
     db: &E,
     cf: &str,
@@ -260,12 +753,14 @@ where
     F: for<'r> FnMut(&'r [(Vec<u8>, Vec<u8>)]),
 {
     let file = box_try!(File::open(path));
-    let mut decoder = if let Some(key_mgr) = key_mgr {
-        let reader = get_decrypter_reader(path, key_mgr)?;
-        BufReader::new(reader)
+    // Decryption happens first so the compressor operates on logical bytes, the
+    // mirror of the building path.
+    let reader: Box<dyn Read> = if let Some(key_mgr) = key_mgr {
+        get_decrypter_reader(path, key_mgr)?
     } else {
-        BufReader::new(Box::new(file) as Box<dyn Read + Send>)
+        Box::new(file)
     };
+    let mut decoder = BufReader::new(archive.reader(reader)?);
 
     let mut wb = db.write_batch();
     let mut write_to_db = |batch: &mut Vec<(Vec<u8>, Vec<u8>)>| -> Result<(), EngineError> {
@@ -281,6 +776,7 @@ where
     // times.
     let mut batch = Vec::with_capacity(1024);
     let mut batch_data_size = 0;
+    let mut digest = crc32fast::Hasher::new();
 
     loop {
         if stale_detector.is_stale() {
@@ -288,12 +784,38 @@ where
         }
         let key = box_try!(decoder.decode_compact_bytes());
         if key.is_empty() {
+            // Verify the whole-file checksum trailer before committing the last
+            // batch, so corrupted/tampered plain files are rejected rather than
+            // silently ingested. Snapshots produced before this trailer existed
+            // have no bytes left here, which `read_exact` reports as EOF — treat
+            // that as "no checksum available" for backward compatibility.
+            let mut trailer = [0u8; 4];
+            match decoder.read_exact(&mut trailer) {
+                Ok(()) => {
+                    let expected = u32::from_le_bytes(trailer);
+                    let actual = digest.finalize();
+                    if expected != actual {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "plain cf file {} checksum mismatch: expected {}, got {}",
+                                path, expected, actual
+                            ),
+                        )
+                        .into());
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+                Err(e) => return Err(e.into()),
+            }
             if !batch.is_empty() {
                 box_try!(write_to_db(&mut batch));
             }
             return Ok(());
         }
         let value = box_try!(decoder.decode_compact_bytes());
+        digest.update(&key);
+        digest.update(&value);
         batch_data_size += key.len() + value.len();
         batch.push((key, value));
         if batch_data_size >= batch_size {
@@ -309,6 +831,7 @@ pub fn apply_sst_cf_files_by_ingest<E>(
     cf: &str,
     start_key: Vec<u8>,
     end_key: Vec<u8>,
+    global_seqno: Option<u64>,
 ) -> Result<(), Error>
 where
     E: KvEngine,
@@ -319,6 +842,14 @@ where
             cf, files
         );
     }
+    // When a `global_seqno` is requested, every key in the ingested files is
+    // assigned this single version at ingest time, independent of whatever the
+    // SST contents encode. This lets callers order snapshot-applied data against
+    // concurrent writes (e.g. the compaction-filter writes into the default CF
+    // noted below) by making the whole batch uniformly newer or older.
+    if let Some(seqno) = global_seqno {
+        box_try!(db.reset_global_seqno_cf(cf, files, seqno));
+    }
     // We set start_key and end_key to enable RocksDB
     // IngestExternalFileOptions.allow_write = true, minimizing the impact on
     // foreground performance.
@@ -433,14 +964,28 @@ where
     Ok(())
 }
 
-fn create_sst_file_writer<E>(engine: &E, cf: CfName, path: &str) -> Result<E::SstWriter, Error>
+fn create_sst_file_writer<E>(
+    engine: &E,
+    cf: CfName,
+    path: &str,
+    cc: &CfCompression,
+    dict: Option<&[u8]>,
+) -> Result<E::SstWriter, Error>
 where
     E: KvEngine,
 {
-    let builder = E::SstWriterBuilder::new()
+    let mut builder = E::SstWriterBuilder::new()
         .set_db(engine)
         .set_cf(cf)
-        .set_compression_type(Some(SstCompressionType::Zstd));
+        .set_compression_type(cc.codec);
+    if cc.codec == Some(SstCompressionType::Zstd) {
+        if let Some(level) = cc.zstd_level {
+            builder = builder.set_compression_level(level);
+        }
+        if let Some(dict) = dict {
+            builder = builder.set_compression_dict(dict);
+        }
+    }
     let writer = box_try!(builder.build(path));
     Ok(writer)
 }
@@ -515,6 +1060,8 @@ mod tests {
                     let stats = build_plain_cf_file::<KvTestEngine>(
                         &mut cf_file,
                         None,
+                        ArchiveFormat::None,
+                        0,
                         &snap,
                         &keys::data_key(b"a"),
                         &keys::data_end_key(b"z"),
@@ -533,6 +1080,7 @@ mod tests {
                     apply_plain_cf_file(
                         tmp_file_path,
                         None,
+                        ArchiveFormat::None,
                         &detector,
                         &db1,
                         cf,
@@ -600,6 +1148,8 @@ mod tests {
                         *max_file_size,
                         &limiter,
                         db_opt.as_ref().and_then(|opt| opt.get_key_manager()),
+                        &SstCompressionPolicy::default(),
+                        1,
                     )
                     .unwrap();
                     if stats.key_count == 0 {
@@ -630,11 +1180,64 @@ mod tests {
                         .iter()
                         .map(|s| s.as_str())
                         .collect::<Vec<&str>>();
-                    apply_sst_cf_files_by_ingest(&tmp_file_paths, &db1, CF_DEFAULT, vec![], vec![])
-                        .unwrap();
+                    apply_sst_cf_files_by_ingest(
+                        &tmp_file_paths,
+                        &db1,
+                        CF_DEFAULT,
+                        vec![],
+                        vec![],
+                        None,
+                    )
+                    .unwrap();
                     assert_eq_db(&db, &db1);
                 }
             }
         }
     }
+
+    #[test]
+    fn test_cf_build_sst_files_parallel() {
+        let limiter = Limiter::new(f64::INFINITY);
+        let dir = Builder::new().prefix("test-snap-cf-db").tempdir().unwrap();
+        let db = open_test_db_with_100keys(dir.path(), None, None).unwrap();
+        let snap_cf_dir = Builder::new().prefix("test-snap-cf").tempdir().unwrap();
+        let mut cf_file = CfFile {
+            cf: CF_DEFAULT,
+            path: PathBuf::from(snap_cf_dir.path().to_str().unwrap()),
+            file_prefix: "test_sst_parallel".to_string(),
+            file_suffix: SST_FILE_SUFFIX.to_string(),
+            ..Default::default()
+        };
+        // Split the scan across several workers; the merged file list must still
+        // reconstruct exactly the same data as a single-threaded build.
+        let stats = build_sst_cf_file_list::<KvTestEngine>(
+            &mut cf_file,
+            &db,
+            &db.snapshot(),
+            &keys::data_key(b"a"),
+            &keys::data_key(b"z"),
+            u64::MAX,
+            &limiter,
+            None,
+            &SstCompressionPolicy::default(),
+            3,
+        )
+        .unwrap();
+        assert!(stats.key_count > 0);
+        assert_eq!(cf_file.tmp_file_paths().len(), cf_file.file_paths().len());
+
+        let dir1 = Builder::new()
+            .prefix("test-snap-cf-db-apply")
+            .tempdir()
+            .unwrap();
+        let db1: KvTestEngine = open_test_empty_db(dir1.path(), None, None).unwrap();
+        let tmp_file_paths = cf_file.tmp_file_paths();
+        let tmp_file_paths = tmp_file_paths
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>();
+        apply_sst_cf_files_by_ingest(&tmp_file_paths, &db1, CF_DEFAULT, vec![], vec![], None)
+            .unwrap();
+        assert_eq_db(&db, &db1);
+    }
 }
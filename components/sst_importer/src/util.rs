@@ -2,10 +2,11 @@
 
 use std::path::Path;
 
-use encryption::DataKeyManager;
+use encryption::{DataKeyImporter, DataKeyManager};
 use engine_traits::EncryptionKeyManager;
 use external_storage_export::ExternalStorage;
 use file_system::File;
+use tikv_util::warn;
 
 use super::Result;
 
@@ -23,10 +24,73 @@ pub fn prepare_sst_for_ingestion<P: AsRef<Path>, Q: AsRef<Path>>(
     path: P,
     clone: Q,
     encryption_key_manager: Option<&DataKeyManager>,
+    fs_security: Option<bool>,
+) -> Result<()> {
+    // Historical default: hard-link when possible, copy otherwise.
+    let opts = IngestOptions {
+        fs_security,
+        ..IngestOptions::default()
+    };
+    prepare_sst_for_ingestion_with(path, clone, &opts, encryption_key_manager)
+}
+
+/// Declarative policy for staging an SST clone before ingestion, unifying the
+/// behaviours that used to be split between `prepare_sst_for_ingestion`
+/// (link-preferred) and `copy_sst_for_ingestion` (always-copy + writable).
+///
+/// Callers now pick behaviour rather than an entry point and can combine
+/// options the old functions could not express together (e.g. `force_copy` plus
+/// `ensure_writable`).
+#[derive(Clone, Copy)]
+pub struct IngestOptions {
+    /// Hard-link the file when it has a single link (`nlink == 1`), otherwise
+    /// fall back to a copy. Ignored when `force_copy` is set.
+    pub move_files: bool,
+    /// Always copy, even when the file could be hard-linked.
+    pub force_copy: bool,
+    /// Clear the read-only bit on the staged clone if it is set.
+    pub ensure_writable: bool,
+    /// Re-read the staged clone and compare its CRC32 against the source to
+    /// detect a corrupted copy before it is handed to RocksDB.
+    pub verify_checksum: bool,
+    /// Filesystem security auditing of the clone path: `None` disables it,
+    /// `Some(downgrade)` runs [`verify_fs_security`] (warning when `downgrade`).
+    pub fs_security: Option<bool>,
+    /// Reset `rocksdb.external_sst_file.global_seqno` back to `0` on the staged
+    /// clone so a retried move-based ingestion still passes checksum
+    /// validation. Forces a copy (the clone must own its bytes to be patched).
+    pub reset_global_seqno: bool,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        IngestOptions {
+            move_files: true,
+            force_copy: false,
+            ensure_writable: false,
+            verify_checksum: false,
+            fs_security: None,
+            reset_global_seqno: false,
+        }
+    }
+}
+
+/// Stage `path` at `clone` according to `opts`, registering the clone with the
+/// key manager. This is the single implementation behind
+/// [`prepare_sst_for_ingestion`] and [`copy_sst_for_ingestion`].
+pub fn prepare_sst_for_ingestion_with<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    clone: Q,
+    opts: &IngestOptions,
+    encryption_key_manager: Option<&DataKeyManager>,
 ) -> Result<()> {
     #[cfg(unix)]
     use std::os::unix::fs::MetadataExt;
 
+    if let Some(downgrade) = opts.fs_security {
+        verify_fs_security(&clone, downgrade)?;
+    }
+
     let path = path.as_ref().to_str().unwrap();
     let clone = clone.as_ref().to_str().unwrap();
 
@@ -47,7 +111,10 @@ pub fn prepare_sst_for_ingestion<P: AsRef<Path>, Q: AsRef<Path>>(
     #[cfg(not(unix))]
     let nlink = 0;
 
-    if nlink == 1 {
+    // A hard link shares the source inode; patching the seqno in place would
+    // mutate the original too, so resetting the seqno demands an owned copy.
+    let can_link = opts.move_files && !opts.force_copy && !opts.reset_global_seqno && nlink == 1;
+    if can_link {
         // RocksDB must not have this file, we can make a hard link.
         file_system::hard_link(path, clone)
             .map_err(|e| format!("link from {} to {}: {:?}", path, clone, e))?;
@@ -57,11 +124,230 @@ pub fn prepare_sst_for_ingestion<P: AsRef<Path>, Q: AsRef<Path>>(
         file_system::copy_and_sync(path, clone)
             .map_err(|e| format!("copy from {} to {}: {:?}", path, clone, e))?;
     }
+
+    if opts.ensure_writable {
+        let mut pmts = file_system::metadata(clone)?.permissions();
+        if pmts.readonly() {
+            pmts.set_readonly(false);
+            file_system::set_permissions(clone, pmts)?;
+        }
+    }
+
+    if opts.verify_checksum && !can_link {
+        // A hard link shares inode bytes with the source, so only a real copy
+        // can diverge; compare CRC32s to catch a silently corrupted copy.
+        let src_crc = crc32_of_file(path)?;
+        let dst_crc = crc32_of_file(clone)?;
+        if src_crc != dst_crc {
+            let _ = file_system::remove_file(clone);
+            return Err(format!(
+                "staged clone {} crc32 {} != source {} crc32 {}",
+                clone, dst_crc, path, src_crc
+            )
+            .into());
+        }
+    }
+
     // sync clone dir
     File::open(Path::new(clone).parent().unwrap())?.sync_all()?;
     if let Some(key_manager) = encryption_key_manager {
         key_manager.link_file(path, clone)?;
     }
+
+    if opts.reset_global_seqno {
+        // The clone is an owned copy (`can_link` is forced off above), so a
+        // prior move-ingest's seqno rewrite can be normalized back to 0 here.
+        reset_sst_global_seqno(clone, encryption_key_manager)?;
+    }
+    Ok(())
+}
+
+fn crc32_of_file(path: &str) -> Result<u32> {
+    use std::io::Read;
+
+    let mut f = File::open(path)?;
+    let mut digest = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        digest.update(&buf[..n]);
+    }
+    Ok(digest.finalize())
+}
+
+/// Reason an ancestor walked by [`verify_fs_security`] is unsafe to host key
+/// material under, independent of any actual filesystem access so it can be
+/// unit tested with synthetic metadata.
+#[cfg(unix)]
+fn ancestor_security_problem(
+    mode: u32,
+    is_dir: bool,
+    owner_uid: u32,
+    calling_uid: u32,
+) -> Option<String> {
+    // A sticky directory (e.g. `/tmp`, mode `01777`) only lets a user
+    // rename/delete their own entries, so fs-mistrust treats it as safe
+    // despite being other-writable; exempt it before flagging group/other
+    // write bits.
+    let sticky_dir = is_dir && mode & 0o1000 != 0;
+    if mode & 0o022 != 0 && !sticky_dir {
+        return Some(format!(
+            "writable by group/other (mode {:o})",
+            mode & 0o7777
+        ));
+    }
+    // uid 0 is exempt both as the caller (root can read anything) and as the
+    // owner (a root-owned ancestor is the expected shape of any real
+    // deployment, not a foreign takeover of our data path).
+    if calling_uid != 0 && owner_uid != calling_uid && owner_uid != 0 {
+        return Some(format!(
+            "owned by uid {} but we run as uid {}",
+            owner_uid, calling_uid
+        ));
+    }
+    None
+}
+
+/// Audit the filesystem security of `path` and its ancestors, up to and
+/// including the first directory owned by uid 0, before any key material is
+/// staged there. Ancestors above that directory are not checked.
+///
+/// Encrypted SSTs are decrypted into the clone path, so a clone living under a
+/// group/world-writable directory (or one owned by another user) could leak
+/// plaintext or let another user swap the file out from under us. Inspired by
+/// the `fs-mistrust` checks Arti adopted, this walks each ancestor of `path`
+/// and rejects any component that is writable by group/other or owned by a
+/// different uid. In containerized or root-owned environments these checks are
+/// often too strict, so `downgrade_to_warn` turns a rejection into a logged
+/// warning instead of a hard error.
+pub fn verify_fs_security<P: AsRef<Path>>(path: P, downgrade_to_warn: bool) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let uid = unsafe { libc::getuid() };
+        let mut current = Some(path.as_ref());
+        while let Some(p) = current {
+            // A path component that does not exist yet (e.g. the clone itself)
+            // has nothing to audit; only the existing ancestors matter.
+            if let Ok(md) = file_system::metadata(p) {
+                let mode = md.mode();
+                if let Some(reason) =
+                    ancestor_security_problem(mode, md.is_dir(), md.uid(), uid)
+                {
+                    let msg = format!("{} is {}", p.display(), reason);
+                    if downgrade_to_warn {
+                        warn!("insecure filesystem permissions for ingestion"; "detail" => %msg);
+                    } else {
+                        return Err(format!("insecure filesystem permissions: {}", msg).into());
+                    }
+                }
+                // A directory owned by uid 0 is the trusted system boundary
+                // (every real path bottoms out at one, often `/` itself): stop
+                // climbing past it instead of walking on to flag every
+                // root-owned ancestor above the data directory for a non-root
+                // process, which would otherwise fire on virtually every real
+                // deployment.
+                if md.is_dir() && md.uid() == 0 {
+                    break;
+                }
+            }
+            current = p.parent();
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (path, downgrade_to_warn);
+    }
+    Ok(())
+}
+
+/// Batch variant of [`prepare_sst_for_ingestion`] that gives all-or-nothing
+/// key/file consistency when ingesting many SSTs at once.
+///
+/// `prepare_sst_for_ingestion` mutates the key-manager dictionary once per file
+/// (`delete_file` + `link_file`), so ingesting `N` SSTs performs `N`
+/// independent, non-atomic rewrites: a crash midway leaves the dictionary
+/// partially populated relative to the clones actually staged. Here every
+/// clone is staged first and the corresponding key entries are buffered in a
+/// [`DataKeyImporter`]; only after all clones are synced do we commit the whole
+/// batch in a single atomic dictionary rewrite. If any copy/link fails, the
+/// staged clones and buffered key entries are rolled back together.
+pub fn prepare_ssts_for_ingestion<P: AsRef<Path>, Q: AsRef<Path>>(
+    files: &[(P, Q)],
+    encryption_key_manager: Option<&DataKeyManager>,
+) -> Result<()> {
+    #[cfg(unix)]
+    use std::os::unix::fs::MetadataExt;
+
+    let mut staged: Vec<String> = Vec::with_capacity(files.len());
+    let mut importer = encryption_key_manager.map(DataKeyImporter::new);
+
+    // Helper that undoes everything staged so far, leaving the dictionary and
+    // filesystem exactly as they were before the batch started.
+    let rollback = |staged: &[String], importer: Option<DataKeyImporter>| {
+        if let Some(mut importer) = importer {
+            if let Err(e) = importer.rollback() {
+                warn!("failed to roll back key importer"; "err" => ?e);
+            }
+        }
+        for clone in staged {
+            if let Err(e) = file_system::remove_file(clone) {
+                warn!("failed to remove staged clone during rollback"; "clone" => clone, "err" => ?e);
+            }
+        }
+    };
+
+    for (path, clone) in files {
+        let path = path.as_ref().to_str().unwrap();
+        let clone = clone.as_ref().to_str().unwrap();
+
+        let stage = (|| -> Result<()> {
+            if Path::new(clone).exists() {
+                file_system::remove_file(clone)
+                    .map_err(|e| format!("remove {}: {:?}", clone, e))?;
+            }
+
+            #[cfg(unix)]
+            let nlink = file_system::metadata(path)
+                .map_err(|e| format!("read metadata from {}: {:?}", path, e))?
+                .nlink();
+            #[cfg(not(unix))]
+            let nlink = 0;
+
+            if nlink == 1 {
+                file_system::hard_link(path, clone)
+                    .map_err(|e| format!("link from {} to {}: {:?}", path, clone, e))?;
+                File::open(clone)?.sync_all()?;
+            } else {
+                file_system::copy_and_sync(path, clone)
+                    .map_err(|e| format!("copy from {} to {}: {:?}", path, clone, e))?;
+            }
+            File::open(Path::new(clone).parent().unwrap())?.sync_all()?;
+            // Buffer the key entry instead of rewriting the dictionary in place.
+            if let Some(importer) = importer.as_mut() {
+                importer.add(path, clone)?;
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = stage {
+            rollback(&staged, importer);
+            return Err(e);
+        }
+        staged.push(clone.to_owned());
+    }
+
+    // All clones are on disk; commit the buffered key entries atomically.
+    if let Some(mut importer) = importer {
+        if let Err(e) = importer.commit() {
+            rollback(&staged, Some(importer));
+            return Err(e);
+        }
+    }
     Ok(())
 }
 
@@ -72,44 +358,379 @@ pub fn copy_sst_for_ingestion<P: AsRef<Path>, Q: AsRef<Path>>(
     path: P,
     clone: Q,
     encryption_key_manager: Option<&DataKeyManager>,
+    fs_security: Option<bool>,
 ) -> Result<()> {
-    let path = path.as_ref();
+    let opts = IngestOptions {
+        move_files: false,
+        force_copy: true,
+        ensure_writable: true,
+        fs_security,
+        // The copy owns its bytes, so normalize any global seqno a prior
+        // move-ingest stamped into the file, keeping a retried apply idempotent.
+        reset_global_seqno: true,
+        ..IngestOptions::default()
+    };
+    prepare_sst_for_ingestion_with(path, clone, &opts, encryption_key_manager)
+}
+
+/// Stream an SST object out of `ExternalStorage`, stage it as a clone, and
+/// register it with the key manager — the remote-source counterpart of
+/// [`prepare_sst_for_ingestion`].
+///
+/// The object is read in bounded chunks so the whole file never has to be
+/// buffered in memory: each chunk is passed through the encryption
+/// [`DecrypterReader`] (when a key manager is configured), written to `clone`
+/// (synced once the stream ends), and folded into a running size/CRC32 check. If the
+/// streamed bytes do not match `expected_size`/`expected_crc32` the partially
+/// written clone is removed and an error is returned before any key material is
+/// linked. On success the clone is registered exactly as the local path is.
+pub fn prepare_sst_for_ingestion_from_storage<E: ExternalStorage, Q: AsRef<Path>>(
+    storage: &E,
+    name: &str,
+    clone: Q,
+    expected_size: u64,
+    expected_crc32: u32,
+    encryption_key_manager: Option<&DataKeyManager>,
+) -> Result<()> {
+    use std::io::{Read, Write};
+
+    use encryption::DecrypterReader;
+
     let clone = clone.as_ref();
+    let clone_str = clone.to_str().unwrap();
+
     if clone.exists() {
         file_system::remove_file(clone)
             .map_err(|e| format!("remove {}: {:?}", clone.display(), e))?;
     }
-    // always try to remove the file from key manager because the clean up in
-    // rocksdb is not atomic, thus the file may be deleted but key in key
-    // manager is not.
     if let Some(key_manager) = encryption_key_manager {
-        key_manager.delete_file(clone.to_str().unwrap(), None)?;
+        key_manager.delete_file(clone_str, None)?;
     }
 
-    file_system::copy_and_sync(path, clone).map_err(|e| {
-        format!(
-            "copy from {} to {}: {:?}",
-            path.display(),
-            clone.display(),
-            e
-        )
-    })?;
+    // Bridge the storage's async reader into a blocking loop; wrap it in the
+    // decrypter when the source object is encrypted at rest.
+    let mut input = storage.read(name);
+    let enc_info = match encryption_key_manager {
+        Some(mgr) => Some(mgr.get_file(name)?),
+        None => None,
+    };
 
-    let mut pmts = file_system::metadata(clone)?.permissions();
-    if pmts.readonly() {
-        pmts.set_readonly(false);
-        file_system::set_permissions(clone, pmts)?;
+    let mut out = file_system::File::create(clone)
+        .map_err(|e| format!("create {}: {:?}", clone.display(), e))?;
+    let mut digest = crc32fast::Hasher::new();
+    let mut written: u64 = 0;
+    let mut buf = vec![0u8; 64 * 1024];
+
+    // `DecrypterReader` decrypts the ciphertext on the fly; `inner()` lets us
+    // recover the underlying stream position for diagnostics if needed.
+    let mut reader: Box<dyn Read> = match enc_info {
+        Some(info) => {
+            let iv = encryption::Iv::from_slice(&info.iv)?;
+            Box::new(DecrypterReader::new(
+                AsyncReadAsRead::new(&mut input),
+                encryption::from_engine_encryption_method(info.method),
+                &info.key,
+                iv,
+            )?)
+        }
+        None => Box::new(AsyncReadAsRead::new(&mut input)),
+    };
+
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .map_err(|e| format!("read {}: {:?}", name, e))?;
+        if n == 0 {
+            break;
+        }
+        digest.update(&buf[..n]);
+        written += n as u64;
+        out.write_all(&buf[..n])
+            .map_err(|e| format!("write {}: {:?}", clone.display(), e))?;
+    }
+    drop(reader);
+    out.sync_all()
+        .map_err(|e| format!("sync {}: {:?}", clone.display(), e))?;
+
+    let crc32 = digest.finalize();
+    if written != expected_size || crc32 != expected_crc32 {
+        let _ = file_system::remove_file(clone);
+        return Err(format!(
+            "streamed sst {} mismatch: size {}/{}, crc32 {}/{}",
+            name, written, expected_size, crc32, expected_crc32
+        )
+        .into());
     }
 
-    // sync clone dir
     File::open(clone.parent().unwrap())?.sync_all()?;
     if let Some(key_manager) = encryption_key_manager {
-        key_manager.link_file(path.to_str().unwrap(), clone.to_str().unwrap())?;
+        key_manager.link_file(name, clone_str)?;
+    }
+    Ok(())
+}
+
+/// Adapt a `futures_util::io::AsyncRead` into a blocking `std::io::Read` by
+/// driving the future on the current thread. Keeps the streaming path free of
+/// a full in-memory buffer.
+struct AsyncReadAsRead<'a, R> {
+    inner: &'a mut R,
+}
+
+impl<'a, R> AsyncReadAsRead<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        AsyncReadAsRead { inner }
+    }
+}
+
+impl<'a, R> std::io::Read for AsyncReadAsRead<'a, R>
+where
+    R: futures_util::io::AsyncRead + Unpin,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use futures_util::io::AsyncReadExt;
+        futures_executor::block_on(self.inner.read(buf))
+    }
+}
+
+/// Property key RocksDB stores the ingest-time global sequence number under.
+const GLOBAL_SEQNO_PROPERTY: &[u8] = b"rocksdb.external_sst_file.global_seqno";
+
+/// Reset an already-ingested SST's global sequence number back to `0` in place
+/// so it can be re-ingested after a partial failure, without keeping a pristine
+/// copy around.
+///
+/// When RocksDB ingests a file with `move_files`, it rewrites the
+/// `rocksdb.external_sst_file.global_seqno` property to the assigned seqno; a
+/// later retry then fails checksum validation because the bytes no longer match
+/// the original. This locates that property, overwrites its 8-byte
+/// little-endian value with `0`, and recomputes the CRC32C trailer of the
+/// containing properties block so the footer still validates.
+///
+/// If the file is still live in RocksDB (detected via `nlink > 1`) mutating it
+/// could corrupt the running DB, so we refuse and return an error instead.
+pub fn reset_sst_global_seqno<P: AsRef<Path>>(
+    path: P,
+    encryption_key_manager: Option<&DataKeyManager>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let path = path.as_ref();
+    let path_str = path.to_str().unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let nlink = file_system::metadata(path)
+            .map_err(|e| format!("read metadata from {}: {:?}", path_str, e))?
+            .nlink();
+        if nlink > 1 {
+            return Err(format!(
+                "refuse to reset global seqno of {}: still live in rocksdb (nlink {})",
+                path_str, nlink
+            )
+            .into());
+        }
     }
 
+    // Read the (decrypted) file into memory, patch it, and write it back under
+    // the same encryption parameters. Operating on plaintext bytes avoids
+    // having to reason about per-block CTR keystream offsets.
+    let enc_info = match encryption_key_manager {
+        Some(mgr) => Some(mgr.get_file(path_str)?),
+        None => None,
+    };
+    let mut data = read_decrypted(path_str, enc_info.as_ref())?;
+
+    patch_global_seqno(&mut data)?;
+
+    // Rewrite atomically via a temp file so a crash never leaves a torn SST.
+    let tmp = format!("{}.seqno.tmp", path_str);
+    {
+        let mut f = file_system::File::create(&tmp)
+            .map_err(|e| format!("create {}: {:?}", tmp, e))?;
+        match &enc_info {
+            Some(info) => {
+                let iv = encryption::Iv::from_slice(&info.iv)?;
+                let mut w = encryption::EncrypterWriter::new(
+                    f,
+                    encryption::from_engine_encryption_method(info.method),
+                    &info.key,
+                    iv,
+                )?;
+                w.write_all(&data)
+                    .map_err(|e| format!("write {}: {:?}", tmp, e))?;
+                f = w.finalize().map_err(|e| format!("finalize {}: {:?}", tmp, e))?;
+            }
+            None => {
+                f.write_all(&data)
+                    .map_err(|e| format!("write {}: {:?}", tmp, e))?;
+            }
+        }
+        f.sync_all().map_err(|e| format!("sync {}: {:?}", tmp, e))?;
+    }
+    file_system::rename(&tmp, path_str).map_err(|e| format!("rename {}: {:?}", tmp, e))?;
+    File::open(path.parent().unwrap())?.sync_all()?;
     Ok(())
 }
 
+fn read_decrypted(
+    path: &str,
+    enc_info: Option<&encryption::FileEncryptionInfo>,
+) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut buf = Vec::new();
+    match enc_info {
+        Some(info) => {
+            let iv = encryption::Iv::from_slice(&info.iv)?;
+            let mut r = encryption::DecrypterReader::new(
+                File::open(path)?,
+                encryption::from_engine_encryption_method(info.method),
+                &info.key,
+                iv,
+            )?;
+            r.read_to_end(&mut buf)?;
+        }
+        None => {
+            File::open(path)?.read_to_end(&mut buf)?;
+        }
+    }
+    Ok(buf)
+}
+
+/// Locate the global-seqno property inside the BlockBasedTable properties block,
+/// zero its value, and fix up the block's CRC32C trailer.
+fn patch_global_seqno(data: &mut [u8]) -> Result<()> {
+    let props = locate_properties_block(data)?;
+    // The block payload is `[props.start, props.start + props.size)`; the 1-byte
+    // compression type and 4-byte masked CRC32C follow immediately after.
+    let value_off = find_property_value_offset(&data[props.start..props.start + props.size])
+        .map(|off| props.start + off)
+        .ok_or_else(|| "global_seqno property not found in sst".to_owned())?;
+    if value_off + 8 > props.start + props.size {
+        return Err("global_seqno property value truncated".to_owned().into());
+    }
+    data[value_off..value_off + 8].copy_from_slice(&0u64.to_le_bytes());
+
+    // Recompute the masked CRC32C over the block contents plus the compression
+    // type byte, exactly as BlockBasedTable writes it.
+    let trailer = props.start + props.size;
+    let crc = crc32c::crc32c(&data[props.start..trailer + 1]);
+    let masked = (crc >> 15 | crc << 17).wrapping_add(0xa282_ead8);
+    data[trailer + 1..trailer + 5].copy_from_slice(&masked.to_le_bytes());
+    Ok(())
+}
+
+struct BlockHandle {
+    start: usize,
+    size: usize,
+}
+
+/// Parse the table footer and metaindex to find the properties block handle.
+fn locate_properties_block(data: &[u8]) -> Result<BlockHandle> {
+    // BlockBasedTable footer is a fixed 53 bytes: metaindex handle, index
+    // handle, zero padding, an 8-byte magic number and the format version.
+    const FOOTER_LEN: usize = 53;
+    if data.len() < FOOTER_LEN {
+        return Err("sst smaller than table footer".to_owned().into());
+    }
+    // For format_version >= 1 (the TiKV default) the footer opens with a
+    // 1-byte checksum-type tag that precedes the metaindex handle; skip it
+    // before decoding the handles.
+    let footer = &data[data.len() - FOOTER_LEN + 1..];
+    let (metaindex, _) = decode_block_handle(footer)?;
+    let meta = read_block(data, &metaindex)?;
+    // Metaindex entries map a block name to its handle; scan for the properties
+    // block (`rocksdb.properties`).
+    let off = find_block_handle_for(meta, b"rocksdb.properties")
+        .ok_or_else(|| "properties block not found in metaindex".to_owned())?;
+    let (handle, _) = decode_block_handle(&meta[off..])?;
+    Ok(handle)
+}
+
+fn read_block<'a>(data: &'a [u8], handle: &BlockHandle) -> Result<&'a [u8]> {
+    if handle.start + handle.size > data.len() {
+        return Err("block handle out of range".to_owned().into());
+    }
+    Ok(&data[handle.start..handle.start + handle.size])
+}
+
+fn decode_block_handle(mut buf: &[u8]) -> Result<(BlockHandle, usize)> {
+    let start_before = buf.len();
+    let (start, rest) = decode_varint64(buf)?;
+    buf = rest;
+    let (size, rest) = decode_varint64(buf)?;
+    let consumed = start_before - rest.len();
+    Ok((
+        BlockHandle {
+            start: start as usize,
+            size: size as usize,
+        },
+        consumed,
+    ))
+}
+
+fn decode_varint64(buf: &[u8]) -> Result<(u64, &[u8])> {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    for (i, &b) in buf.iter().enumerate() {
+        result |= u64::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            return Ok((result, &buf[i + 1..]));
+        }
+        shift += 7;
+        if shift >= 64 {
+            break;
+        }
+    }
+    Err("malformed varint".to_owned().into())
+}
+
+/// Find, inside a metaindex block, the byte offset of the handle encoded for
+/// `name`. Metaindex entries are `varint shared | varint non_shared | varint
+/// value_len | key | value`; with no prefix compression `shared` is 0.
+fn find_block_handle_for(block: &[u8], name: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i + 3 < block.len() {
+        let (shared, r1) = decode_varint64(&block[i..]).ok()?;
+        let (non_shared, r2) = decode_varint64(r1).ok()?;
+        let (value_len, r3) = decode_varint64(r2).ok()?;
+        let key_off = block.len() - r3.len();
+        let key_end = key_off + non_shared as usize;
+        if key_end > block.len() {
+            return None;
+        }
+        if shared == 0 && &block[key_off..key_end] == name {
+            return Some(key_end);
+        }
+        i = key_end + value_len as usize;
+    }
+    None
+}
+
+/// Within the properties block body, find the file offset of the global-seqno
+/// value. Properties are encoded as metaindex-style entries whose value is the
+/// raw 8-byte seqno.
+fn find_property_value_offset(block: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i + 3 < block.len() {
+        let (shared, r1) = decode_varint64(&block[i..]).ok()?;
+        let (non_shared, r2) = decode_varint64(r1).ok()?;
+        let (value_len, r3) = decode_varint64(r2).ok()?;
+        let key_off = block.len() - r3.len();
+        let key_end = key_off + non_shared as usize;
+        let value_end = key_end + value_len as usize;
+        if value_end > block.len() {
+            return None;
+        }
+        if shared == 0 && &block[key_off..key_end] == GLOBAL_SEQNO_PROPERTY {
+            return Some(key_end);
+        }
+        i = value_end;
+    }
+    None
+}
+
 pub fn url_for<E: ExternalStorage>(storage: &E) -> String {
     storage
         .url()
@@ -133,7 +754,13 @@ mod tests {
     use tempfile::Builder;
     use test_util::encryption::new_test_key_manager;
 
-    use super::{copy_sst_for_ingestion, prepare_sst_for_ingestion};
+    #[cfg(unix)]
+    use super::ancestor_security_problem;
+    use super::{
+        copy_sst_for_ingestion, decode_block_handle, decode_varint64, find_block_handle_for,
+        find_property_value_offset, locate_properties_block, patch_global_seqno,
+        prepare_sst_for_ingestion, verify_fs_security, GLOBAL_SEQNO_PROPERTY,
+    };
 
     #[cfg(unix)]
     fn check_hard_link<P: AsRef<Path>>(path: P, nlink: u64) {
@@ -203,11 +830,11 @@ mod tests {
 
         // The first ingestion will hard link sst_path to sst_clone.
         check_hard_link(&sst_path, 1);
-        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager).unwrap();
+        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager, None).unwrap();
         check_hard_link(&sst_path, 2);
         check_hard_link(&sst_clone, 2);
         // If we prepare again, it will use hard link too.
-        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager).unwrap();
+        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager, None).unwrap();
         check_hard_link(&sst_path, 2);
         check_hard_link(&sst_clone, 2);
         db.ingest_external_file_cf(
@@ -229,7 +856,7 @@ mod tests {
 
         // The second ingestion will copy sst_path to sst_clone.
         check_hard_link(&sst_path, 2);
-        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager).unwrap();
+        prepare_sst_for_ingestion(&sst_path, &sst_clone, key_manager, None).unwrap();
         check_hard_link(&sst_path, 2);
         check_hard_link(&sst_clone, 1);
         db.ingest_external_file_cf(
@@ -307,11 +934,11 @@ mod tests {
 
         gen_sst_with_kvs(&db, CF_DEFAULT, sst_path.to_str().unwrap(), &kvs);
 
-        copy_sst_for_ingestion(&sst_path, &sst_clone, None).unwrap();
+        copy_sst_for_ingestion(&sst_path, &sst_clone, None, None).unwrap();
         check_hard_link(&sst_path, 1);
         check_hard_link(&sst_clone, 1);
 
-        copy_sst_for_ingestion(&sst_path, &sst_clone, None).unwrap();
+        copy_sst_for_ingestion(&sst_path, &sst_clone, None, None).unwrap();
         check_hard_link(&sst_path, 1);
         check_hard_link(&sst_clone, 1);
 
@@ -325,4 +952,195 @@ mod tests {
         check_db_with_kvs(&db, CF_DEFAULT, &kvs);
         assert!(!sst_clone.exists());
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_ancestor_security_problem() {
+        // World-writable is rejected regardless of ownership.
+        assert!(ancestor_security_problem(0o777, true, 1000, 1000).is_some());
+        // A sticky world-writable dir (e.g. `/tmp`) is exempt.
+        assert!(ancestor_security_problem(0o1777, true, 1000, 1000).is_none());
+        // Foreign-owned ancestor is rejected for a non-root caller.
+        assert!(ancestor_security_problem(0o755, true, 5000, 1000).is_some());
+        // A root-owned ancestor is never rejected: every real path bottoms
+        // out at one, so flagging it would fire on virtually every
+        // deployment. This is the regression case for the bug where the walk
+        // used to always trip once it reached a root-owned component (e.g.
+        // `/` itself).
+        assert!(ancestor_security_problem(0o755, true, 0, 1000).is_none());
+        // A root caller never flags ownership, even of a foreign-owned path.
+        assert!(ancestor_security_problem(0o755, true, 5000, 0).is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_verify_fs_security() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = Builder::new()
+            .prefix("_util_test_verify_fs_security")
+            .tempdir()
+            .unwrap();
+        let mid = root.path().join("mid");
+        let leaf = mid.join("leaf");
+        std::fs::create_dir_all(&leaf).unwrap();
+        let file = leaf.join("clone.sst");
+        std::fs::write(&file, b"data").unwrap();
+
+        // A private, unshared directory tree is safe.
+        verify_fs_security(&file, false).unwrap();
+
+        // A group/other-writable ancestor is rejected...
+        std::fs::set_permissions(&mid, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(verify_fs_security(&file, false).is_err());
+        // ...unless downgraded to a warning.
+        verify_fs_security(&file, true).unwrap();
+
+        // A sticky world-writable ancestor (like `/tmp`) is exempt.
+        std::fs::set_permissions(&mid, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        verify_fs_security(&file, false).unwrap();
+
+        std::fs::set_permissions(&mid, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        // The walk climbs past `root` and terminates at a root-owned ancestor
+        // (e.g. `/tmp` or `/`) without error, even though that ancestor isn't
+        // owned by whichever uid is running the test.
+        verify_fs_security(&file, false).unwrap();
+    }
+
+    #[test]
+    fn test_decode_varint64() {
+        // Single-byte varints (value < 128) round-trip with no continuation bit.
+        assert_eq!(decode_varint64(&[0x00]).unwrap().0, 0);
+        assert_eq!(decode_varint64(&[0x7f]).unwrap().0, 127);
+        // Two-byte varint: 128 is encoded as 0x80, 0x01.
+        let (v, rest) = decode_varint64(&[0x80, 0x01, 0xff]).unwrap();
+        assert_eq!(v, 128);
+        assert_eq!(rest, &[0xff]);
+        // 300 = 0b1_0010_1100 -> low 7 bits 0101100 with continuation, then 10.
+        assert_eq!(decode_varint64(&[0xac, 0x02]).unwrap().0, 300);
+        // A buffer that never clears its continuation bit is malformed.
+        assert!(decode_varint64(&[0x80, 0x80, 0x80]).is_err());
+    }
+
+    #[test]
+    fn test_decode_block_handle() {
+        // offset=128 (0x80, 0x01), size=5 (0x05), followed by unrelated bytes.
+        let buf = [0x80, 0x01, 0x05, 0xaa, 0xbb];
+        let (handle, consumed) = decode_block_handle(&buf).unwrap();
+        assert_eq!(handle.start, 128);
+        assert_eq!(handle.size, 5);
+        assert_eq!(consumed, 3);
+    }
+
+    /// Build a metaindex/properties-style block: each entry is `varint shared
+    /// (always 0 here) | varint non_shared | varint value_len | key | value`,
+    /// matching what [`find_block_handle_for`] and [`find_property_value_offset`]
+    /// scan.
+    fn encode_block_entry(key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.push(0); // shared
+        buf.push(key.len() as u8);
+        buf.push(value.len() as u8);
+        buf.extend_from_slice(key);
+        buf.extend_from_slice(value);
+        buf
+    }
+
+    #[test]
+    fn test_find_block_handle_for() {
+        let mut block = encode_block_entry(b"rocksdb.range_del", &[0x10, 0x02]);
+        block.extend(encode_block_entry(b"rocksdb.properties", &[0x20, 0x03]));
+
+        let off = find_block_handle_for(&block, b"rocksdb.properties").unwrap();
+        let (handle, _) = decode_block_handle(&block[off..]).unwrap();
+        assert_eq!(handle.start, 0x20);
+        assert_eq!(handle.size, 0x03);
+
+        assert!(find_block_handle_for(&block, b"rocksdb.nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_find_property_value_offset() {
+        let mut block = encode_block_entry(b"rocksdb.other_property", b"whatever");
+        let seqno_entry_off = block.len();
+        block.extend(encode_block_entry(GLOBAL_SEQNO_PROPERTY, &42u64.to_le_bytes()));
+
+        let off = find_property_value_offset(&block).unwrap();
+        // The value immediately follows the key within the same entry.
+        let key_len = GLOBAL_SEQNO_PROPERTY.len();
+        assert_eq!(off, seqno_entry_off + 3 + key_len);
+        assert_eq!(&block[off..off + 8], &42u64.to_le_bytes());
+
+        assert!(find_property_value_offset(b"no properties here").is_none());
+    }
+
+    #[test]
+    fn test_locate_properties_block_rejects_truncated_footer() {
+        assert!(locate_properties_block(&[0u8; 10]).is_err());
+    }
+
+    #[test]
+    fn test_patch_global_seqno_on_real_multi_block_sst() {
+        let path = Builder::new()
+            .prefix("_util_test_patch_global_seqno")
+            .tempdir()
+            .unwrap();
+        let path_str = path.path().to_str().unwrap();
+
+        let sst_dir = Builder::new()
+            .prefix("_util_test_patch_global_seqno_sst")
+            .tempdir()
+            .unwrap();
+        let sst_path = sst_dir.path().join("abc.sst");
+
+        // Enough keys/values to spill across many of RocksDB's (4 KiB default)
+        // data blocks, so the footer/metaindex scan this exercises is over a
+        // real multi-block table, not a single-block toy file.
+        let kvs: Vec<(String, String)> = (0..500)
+            .map(|i| (format!("k{:05}", i), "v".repeat(64)))
+            .collect();
+        let kv_refs: Vec<(&str, &str)> = kvs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+
+        let db_opts = RocksDbOptions::default();
+        let cf_opts = vec![(CF_DEFAULT, RocksCfOptions::default())];
+        let db = new_engine_opt(path_str, db_opts, cf_opts).unwrap();
+        gen_sst_with_kvs(&db, CF_DEFAULT, sst_path.to_str().unwrap(), &kv_refs);
+
+        let mut data = std::fs::read(&sst_path).unwrap();
+
+        // Locate the global-seqno property and simulate what an `ingest
+        // --move_files` rewrite leaves behind: a nonzero seqno.
+        let props = locate_properties_block(&data).unwrap();
+        let value_off = find_property_value_offset(&data[props.start..props.start + props.size])
+            .map(|off| props.start + off)
+            .unwrap();
+        data[value_off..value_off + 8].copy_from_slice(&7u64.to_le_bytes());
+        // Corrupting the value without fixing up the trailer would leave a
+        // mismatched checksum; recompute it the same way BlockBasedTable does
+        // so the later ingest below actually exercises CRC validation.
+        let trailer = props.start + props.size;
+        let crc = crc32c::crc32c(&data[props.start..trailer + 1]);
+        let masked = (crc >> 15 | crc << 17).wrapping_add(0xa282_ead8);
+        data[trailer + 1..trailer + 5].copy_from_slice(&masked.to_le_bytes());
+
+        patch_global_seqno(&mut data).unwrap();
+        let value_off = find_property_value_offset(&data[props.start..props.start + props.size])
+            .map(|off| props.start + off)
+            .unwrap();
+        assert_eq!(&data[value_off..value_off + 8], &0u64.to_le_bytes());
+
+        // The patched bytes still form a valid SST: write them out and let
+        // RocksDB itself validate the footer/block checksums by ingesting it.
+        let patched_path = sst_dir.path().join("abc.patched.sst");
+        std::fs::write(&patched_path, &data).unwrap();
+        db.ingest_external_file_cf(
+            CF_DEFAULT,
+            &[patched_path.to_str().unwrap()],
+            None,
+            false, // force_allow_write
+        )
+        .unwrap();
+        check_db_with_kvs(&db, CF_DEFAULT, &kv_refs);
+    }
 }
@@ -1,5 +1,6 @@
 // Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
 use std::{
+    collections::HashMap,
     fs::{File, Metadata},
     io::{Read, Write},
     path::PathBuf,
@@ -10,6 +11,7 @@ use std::{
 };
 
 use chrono::{offset::Local, DateTime};
+use flate2::{write::GzEncoder, Compression};
 use futures::{
     channel::oneshot::{self, Sender},
     future::BoxFuture,
@@ -33,12 +35,15 @@ use self::test_utils::{activate_prof, deactivate_prof, dump_prof};
 // File name suffix for periodically dumped heap profiles.
 pub const HEAP_PROFILE_SUFFIX: &str = ".heap";
 pub const HEAP_PROFILE_REGEX: &str = r"^[0-9]{6,6}\.heap$";
+// File name suffix for periodically dumped CPU profiles (gzipped pprof).
+pub const CPU_PROFILE_SUFFIX: &str = ".pprof";
+pub const CPU_PROFILE_REGEX: &str = r"^[0-9]{6,6}\.pprof$";
 
 lazy_static! {
-    // If it's some it means there are already a CPU profiling.
-    static ref CPU_PROFILE_ACTIVE: Mutex<Option<()>> = Mutex::new(None);
-    // If it's some it means there are already a heap profiling. The channel is used to deactivate a profiling.
-    pub static ref HEAP_PROFILE_ACTIVE: Mutex<Option<Option<(Sender<()>, TempDir)>>> = Mutex::new(None);
+    // Registry of active CPU profiling sessions, keyed by `SessionId`.
+    static ref CPU_PROFILE_REGISTRY: Mutex<ProfileRegistry> = Mutex::new(ProfileRegistry::default());
+    // Registry of active heap profiling sessions, keyed by `SessionId`.
+    static ref HEAP_PROFILE_REGISTRY: Mutex<ProfileRegistry> = Mutex::new(ProfileRegistry::default());
 
     // To normalize thread names.
     static ref THREAD_NAME_RE: Regex =
@@ -46,6 +51,72 @@ lazy_static! {
     static ref THREAD_NAME_REPLACE_SEPERATOR_RE: Regex = Regex::new(r"[_ ]").unwrap();
 }
 
+/// A handle identifying a profiling session. Allocated by
+/// `activate_*`/`start_one_*` and passed to the matching `deactivate_*`,
+/// `list_*` and dump helpers so several sessions can be tracked by id instead
+/// of all sharing one process-wide slot. For heap profiling this really does
+/// let independent sessions run side by side. CPU profiling is bookkeeping
+/// only: `pprof::ProfilerGuard` wraps one process-global profiler, so at most
+/// one CPU session can be actively sampling. A second `start_one_cpu_profile`
+/// call made while one is running fails fast in `on_start`, before it is ever
+/// assigned a `SessionId`. A second `activate_cpu_profile` call is assigned a
+/// `SessionId` first and then fails when its dump loop cannot build a
+/// profiler guard, which ends the session and unregisters it. Neither call
+/// collides with or replaces the first session's guard.
+pub type SessionId = u64;
+
+// State tracked for one active profiling session.
+struct ProfileSession {
+    // Notifies the session's dump loop to stop. `None` for one-shot sessions,
+    // or once a cancellation has already been delivered.
+    cancel: Option<Sender<()>>,
+    // Temp dir holding the session's periodic dumps, if any.
+    dir: Option<TempDir>,
+}
+
+#[derive(Default)]
+struct ProfileRegistry {
+    next_id: SessionId,
+    sessions: HashMap<SessionId, ProfileSession>,
+}
+
+impl ProfileRegistry {
+    fn register(&mut self, session: ProfileSession) -> SessionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sessions.insert(id, session);
+        id
+    }
+
+    fn unregister(&mut self, id: SessionId) {
+        self.sessions.remove(&id);
+    }
+
+    // Deliver a cancellation to the session. Returns `false` if the id is
+    // unknown.
+    fn cancel(&mut self, id: SessionId) -> bool {
+        match self.sessions.get_mut(&id) {
+            Some(session) => {
+                if let Some(tx) = session.cancel.take() {
+                    let _ = tx.send(());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn dir(&self, id: SessionId) -> Option<PathBuf> {
+        self.sessions
+            .get(&id)
+            .and_then(|s| s.dir.as_ref().map(|d| d.path().to_owned()))
+    }
+
+    fn contains(&self, id: SessionId) -> bool {
+        self.sessions.contains_key(&id)
+    }
+}
+
 type OnEndFn<I, T> = Box<dyn FnOnce(I) -> Result<T, String> + Send + 'static>;
 
 struct ProfileRunner<I, T> {
@@ -93,20 +164,49 @@ impl<I, T> Future for ProfileRunner<I, T> {
     }
 }
 
-/// Trigger a heap profile and return the content.
-pub fn dump_one_heap_profile() -> Result<Vec<u8>, String> {
-    if HEAP_PROFILE_ACTIVE.lock().unwrap().is_none() {
+/// Trigger a heap profile for the given session and return the content.
+pub async fn dump_one_heap_profile(id: SessionId) -> Result<Vec<u8>, String> {
+    if !HEAP_PROFILE_REGISTRY.lock().unwrap().contains(id) {
         return Err("heap profiling is not activated".to_owned());
     }
-    let f = NamedTempFile::new().map_err(|e| format!("create tmp file fail: {}", e))?;
-    let path = f.path().to_str().unwrap();
-    dump_prof(path).map_err(|e| format!("dump_prof: {}", e))?;
-    read_file(path)
+    // Dumping a jemalloc profile and reading it back are blocking filesystem
+    // operations; run them on a dedicated thread so a large dump or a slow disk
+    // can't stall unrelated futures sharing the async worker.
+    let body = spawn_blocking_io(|| {
+        let f = NamedTempFile::new().map_err(|e| format!("create tmp file fail: {}", e))?;
+        let path = f.path().to_str().unwrap();
+        dump_prof(path).map_err(|e| format!("dump_prof: {}", e))?;
+        read_file(path)
+    })
+    .await?;
+    // Emit canonical (gzip-wrapped) pprof so the payload can be scraped
+    // directly by standard pprof tooling without a manual re-encode step.
+    gzip_encode(&body)
+}
+
+/// Run a blocking, potentially slow filesystem closure on a dedicated blocking
+/// thread and deliver its result back through a channel, keeping the profiling
+/// I/O off the shared async executor.
+async fn spawn_blocking_io<F, T>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    std::thread::Builder::new()
+        .name("prof-io".to_owned())
+        .spawn(move || {
+            let _ = tx.send(f());
+        })
+        .map_err(|e| format!("spawn profiling io thread fail: {}", e))?;
+    rx.await
+        .map_err(|_| "profiling io thread canceled".to_owned())?
 }
 
-/// Activate heap profile and call `callback` if successfully.
-/// `deactivate_heap_profile` can only be called after it's notified from
-/// `callback`.
+/// Activate heap profile and call `callback` with the allocated [`SessionId`]
+/// once it's successfully started. The id is passed to
+/// [`deactivate_heap_profile`] and the `*_heap_profile*` helpers, so several
+/// independent heap profiling sessions can coexist.
 pub async fn activate_heap_profile<S, F>(
     dump_period: Option<S>,
     store_path: PathBuf,
@@ -114,12 +214,8 @@ pub async fn activate_heap_profile<S, F>(
 ) -> Result<(), String>
 where
     S: Stream<Item = Result<(), String>> + Send + Unpin + 'static,
-    F: FnOnce() + Send + 'static,
+    F: FnOnce(SessionId) + Send + 'static,
 {
-    if HEAP_PROFILE_ACTIVE.lock().unwrap().is_some() {
-        return Err("Already in Heap Profiling".to_owned());
-    }
-
     let (tx, rx) = oneshot::channel();
     let dir = tempfile::Builder::new()
         .prefix("heap-")
@@ -128,18 +224,19 @@ where
     let dir_path = dir.path().to_str().unwrap().to_owned();
 
     let on_start = move || {
-        let mut activate = HEAP_PROFILE_ACTIVE.lock().unwrap();
-        assert!(activate.is_none());
-        *activate = Some(Some((tx, dir)));
+        let id = HEAP_PROFILE_REGISTRY.lock().unwrap().register(ProfileSession {
+            cancel: Some(tx),
+            dir: Some(dir),
+        });
         activate_prof().map_err(|e| format!("activate_prof: {}", e))?;
-        callback();
-        info!("periodical heap profiling is started");
-        Ok(())
+        callback(id);
+        info!("periodical heap profiling is started"; "session" => id);
+        Ok(id)
     };
 
-    let on_end = |_| {
+    let on_end = |id: SessionId| {
         let res = deactivate_prof().map_err(|e| format!("deactivate_prof: {}", e));
-        *HEAP_PROFILE_ACTIVE.lock().unwrap() = None;
+        HEAP_PROFILE_REGISTRY.lock().unwrap().unregister(id);
         res
     };
 
@@ -165,72 +262,119 @@ where
     ProfileRunner::new(on_start, on_end, end.boxed())?.await
 }
 
-/// Deactivate heap profile. Return `false` if it hasn't been activated.
-pub fn deactivate_heap_profile() -> bool {
-    let mut activate = HEAP_PROFILE_ACTIVE.lock().unwrap();
-    match activate.as_mut() {
-        Some(tx) => {
-            if let Some((tx, _)) = tx.take() {
-                let _ = tx.send(());
-            } else {
-                *activate = None;
+/// Deactivate the heap profiling session with the given id. Return `false` if
+/// it isn't active.
+pub fn deactivate_heap_profile(id: SessionId) -> bool {
+    HEAP_PROFILE_REGISTRY.lock().unwrap().cancel(id)
+}
+
+/// Activate continuous CPU profiling. Mirrors [`activate_heap_profile`]: given
+/// a `dump_period` stream it periodically flushes an aggregated pprof profile
+/// into numbered files under a temp dir in `store_path`. Memory stays bounded
+/// because each flush boundary starts a fresh profiler guard, so only one
+/// interval worth of stack samples is ever held in the signal-driven
+/// aggregator before being serialized and reset.
+pub async fn activate_cpu_profile<S, F>(
+    dump_period: S,
+    frequency: i32,
+    store_path: PathBuf,
+    callback: F,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<(), String>> + Send + Unpin + 'static,
+    F: FnOnce(SessionId) + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let dir = tempfile::Builder::new()
+        .prefix("cpu-")
+        .tempdir_in(store_path)
+        .map_err(|e| format!("create temp directory: {}", e))?;
+    let dir_path = dir.path().to_str().unwrap().to_owned();
+
+    let on_start = move || {
+        let id = CPU_PROFILE_REGISTRY.lock().unwrap().register(ProfileSession {
+            cancel: Some(tx),
+            dir: Some(dir),
+        });
+        callback(id);
+        info!("continuous CPU profiling is started"; "session" => id);
+        Ok(id)
+    };
+
+    let on_end = |id: SessionId| {
+        CPU_PROFILE_REGISTRY.lock().unwrap().unregister(id);
+        Ok(())
+    };
+
+    let end = async move {
+        select! {
+            _ = rx.fuse() => {
+                info!("continuous CPU profiling is canceled");
+                Ok(())
+            },
+            res = dump_cpu_profile_periodically(dump_period, frequency, dir_path).fuse() => {
+                warn!("the CPU profiling dump loop shouldn't break"; "res" => ?res);
+                res
             }
-            true
         }
-        None => false,
-    }
+    };
+
+    ProfileRunner::new(on_start, on_end, end.boxed())?.await
+}
+
+/// Deactivate the continuous CPU profiling session with the given id. Return
+/// `false` if it isn't active.
+pub fn deactivate_cpu_profile(id: SessionId) -> bool {
+    CPU_PROFILE_REGISTRY.lock().unwrap().cancel(id)
+}
+
+/// Output format for a one-shot CPU profile produced by
+/// [`start_one_cpu_profile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuProfileFormat {
+    /// A self-contained flamegraph SVG.
+    Flamegraph,
+    /// A gzip-wrapped pprof protobuf profile.
+    Protobuf,
+    /// Brendan Gregg's folded/collapsed stack text, consumed by the FlameGraph
+    /// scripts, inferno and speedscope.
+    Collapsed,
 }
 
 /// Trigger one cpu profile.
 pub async fn start_one_cpu_profile<F>(
     end: F,
     frequency: i32,
-    protobuf: bool,
+    format: CpuProfileFormat,
 ) -> Result<Vec<u8>, String>
 where
     F: Future<Output = Result<(), String>> + Send + 'static,
 {
-    if CPU_PROFILE_ACTIVE.lock().unwrap().is_some() {
-        return Err("Already in CPU Profiling".to_owned());
-    }
-
     let on_start = || {
-        let mut activate = CPU_PROFILE_ACTIVE.lock().unwrap();
-        assert!(activate.is_none());
-        *activate = Some(());
-        let guard = pprof::ProfilerGuardBuilder::default()
-            .frequency(frequency)
-            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
-            .build()
-            .map_err(|e| format!("pprof::ProfilerGuardBuilder::build fail: {}", e))?;
-        Ok(guard)
+        let guard = build_cpu_profiler_guard(frequency)?;
+        let id = CPU_PROFILE_REGISTRY.lock().unwrap().register(ProfileSession {
+            cancel: None,
+            dir: None,
+        });
+        Ok((id, guard))
     };
 
-    let on_end = move |guard: pprof::ProfilerGuard<'static>| {
+    let on_end = move |(id, guard): (SessionId, pprof::ProfilerGuard<'static>)| {
         defer! {
-            *CPU_PROFILE_ACTIVE.lock().unwrap() = None
-        }
-        let report = guard
-            .report()
-            .frames_post_processor(move |frames| {
-                let name = extract_thread_name(&frames.thread_name);
-                frames.thread_name = name;
-            })
-            .build()
-            .map_err(|e| format!("create cpu profiling report fail: {}", e))?;
-        let mut body = Vec::new();
-        if protobuf {
-            let profile = report
-                .pprof()
-                .map_err(|e| format!("generate pprof from report fail: {}", e))?;
-            profile
-                .write_to_vec(&mut body)
-                .map_err(|e| format!("encode pprof into bytes fail: {}", e))?;
-        } else {
-            report
-                .flamegraph(&mut body)
-                .map_err(|e| format!("generate flamegraph from report fail: {}", e))?;
+            CPU_PROFILE_REGISTRY.lock().unwrap().unregister(id)
         }
+        let report = build_cpu_report(&guard)?;
+        let body = match format {
+            CpuProfileFormat::Protobuf => report_to_pprof(&report, frequency)?,
+            CpuProfileFormat::Collapsed => report_to_collapsed(&report)?,
+            CpuProfileFormat::Flamegraph => {
+                let mut body = Vec::new();
+                report
+                    .flamegraph(&mut body)
+                    .map_err(|e| format!("generate flamegraph from report fail: {}", e))?;
+                body
+            }
+        };
         drop(guard);
 
         Ok(body)
@@ -279,16 +423,121 @@ pub fn jeprof_heap_profile(path: &str) -> Result<Vec<u8>, String> {
     Ok(output.stdout)
 }
 
-pub fn heap_profiles_dir() -> Option<PathBuf> {
-    HEAP_PROFILE_ACTIVE
-        .lock()
+/// Run jeprof with `base_path` supplied as the baseline so the resulting SVG
+/// shows only the *growth* in live allocations between the baseline and
+/// `cur_path`, rather than the absolute snapshot. This turns the periodic
+/// `NNNNNN.heap` dumps into a practical leak hunter: pick an older profile as
+/// the baseline and diff a newer one against it.
+pub fn jeprof_heap_profile_diff(base_path: &str, cur_path: &str) -> Result<Vec<u8>, String> {
+    info!("using jeprof to diff {} against {}", cur_path, base_path);
+    let bin = std::env::current_exe().map_err(|e| format!("get current exe path fail: {}", e))?;
+    let mut jeprof = Command::new("perl")
+        .args([
+            "/dev/stdin",
+            "--show_bytes",
+            "--base",
+            base_path,
+            &bin.as_os_str().to_string_lossy(),
+            cur_path,
+            "--svg",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("spawn jeprof fail: {}", e))?;
+    jeprof
+        .stdin
+        .take()
         .unwrap()
-        .as_ref()
-        .and_then(|v| v.as_ref().map(|(_, dir)| dir.path().to_owned()))
+        .write_all(include_bytes!("jeprof.in"))
+        .unwrap();
+    let output = jeprof
+        .wait_with_output()
+        .map_err(|e| format!("jeprof: {}", e))?;
+    if !output.status.success() {
+        let stderr = std::str::from_utf8(&output.stderr).unwrap_or("invalid utf8");
+        return Err(format!("jeprof stderr: {:?}", stderr));
+    }
+    Ok(output.stdout)
+}
+
+fn write_file(path: &str, buf: &[u8]) -> Result<(), String> {
+    let mut f = File::create(path).map_err(|e| format!("create {} fail: {}", path, e))?;
+    f.write_all(buf)
+        .map_err(|e| format!("write {} fail: {}", path, e))
+}
+
+async fn dump_cpu_profile_periodically<S>(
+    mut period: S,
+    frequency: i32,
+    dir: String,
+) -> Result<(), String>
+where
+    S: Stream<Item = Result<(), String>> + Send + Unpin + 'static,
+{
+    let mut id = 0;
+    let mut guard = build_cpu_profiler_guard(frequency)?;
+    while let Some(res) = period.next().await {
+        res?;
+        // Coalesce ticks that accumulated while the previous flush was running.
+        while let Some(Some(res)) = period.next().now_or_never() {
+            res?;
+        }
+        // Snapshot the aggregated samples, then immediately start a fresh guard
+        // so the in-memory aggregator is reset and memory stays bounded.
+        let report = build_cpu_report(&guard)?;
+        // Only one guard can be active process-wide, so the old one has to be
+        // dropped before its replacement is built.
+        drop(guard);
+        guard = build_cpu_profiler_guard(frequency)?;
+        let body = report_to_pprof(&report, frequency)?;
+        id += 1;
+        let path = format!("{}/{:0>6}{}", dir, id, CPU_PROFILE_SUFFIX);
+        spawn_blocking_io(move || write_file(&path, &body)).await?;
+        info!("a CPU profile is dumped with id {:0>6}", id);
+    }
+    Ok(())
+}
+
+pub fn cpu_profiles_dir(id: SessionId) -> Option<PathBuf> {
+    CPU_PROFILE_REGISTRY.lock().unwrap().dir(id)
+}
+
+pub fn list_cpu_profiles(id: SessionId) -> Result<Vec<(String, String)>, String> {
+    let path = match cpu_profiles_dir(id) {
+        Some(path) => path.into_os_string().into_string().unwrap(),
+        None => return Ok(vec![]),
+    };
+
+    let dir = std::fs::read_dir(path).map_err(|e| format!("read dir fail: {}", e))?;
+    let mut profiles = Vec::new();
+    for item in dir {
+        let item = match item {
+            Ok(x) => x,
+            _ => continue,
+        };
+        let f = item.file_name().to_str().unwrap().to_owned();
+        if !f.ends_with(CPU_PROFILE_SUFFIX) {
+            continue;
+        }
+        let ct = item.metadata().map(|x| last_change_epoch(&x)).unwrap();
+        let dt = DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(ct));
+        profiles.push((f, dt.format("%Y-%m-%d %H:%M:%S").to_string()));
+    }
+
+    // Reverse sort them.
+    profiles.sort_by(|x, y| y.1.cmp(&x.1));
+    info!("list_cpu_profiles gets {} items", profiles.len());
+    Ok(profiles)
+}
+
+pub fn heap_profiles_dir(id: SessionId) -> Option<PathBuf> {
+    HEAP_PROFILE_REGISTRY.lock().unwrap().dir(id)
 }
 
-pub fn list_heap_profiles() -> Result<Vec<(String, String)>, String> {
-    let path = match heap_profiles_dir() {
+pub fn list_heap_profiles(id: SessionId) -> Result<Vec<(String, String)>, String> {
+    let path = match heap_profiles_dir(id) {
         Some(path) => path.into_os_string().into_string().unwrap(),
         None => return Ok(vec![]),
     };
@@ -315,6 +564,21 @@ pub fn list_heap_profiles() -> Result<Vec<(String, String)>, String> {
     Ok(profiles)
 }
 
+/// Resolve a profile name returned by [`list_heap_profiles`] to its absolute
+/// path inside the active heap-profiling directory. The name must be a bare
+/// file name so it can't escape the directory.
+pub fn resolve_heap_profile(id: SessionId, name: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name.contains("..") {
+        return Err(format!("invalid heap profile name: {}", name));
+    }
+    let dir = heap_profiles_dir(id).ok_or_else(|| "heap profiling is not activated".to_owned())?;
+    let path = dir.join(name);
+    if !path.exists() {
+        return Err(format!("heap profile {} does not exist", name));
+    }
+    Ok(path.into_os_string().into_string().unwrap())
+}
+
 async fn dump_heap_profile_periodically<S>(mut period: S, dir: String) -> Result<(), String>
 where
     S: Stream<Item = Result<(), String>> + Send + Unpin + 'static,
@@ -322,14 +586,126 @@ where
     let mut id = 0;
     while let Some(res) = period.next().await {
         res?;
+        // Coalesce any ticks that piled up while the previous dump was in
+        // flight, so a slow disk makes the dumper fall behind gracefully
+        // rather than queueing an unbounded backlog of dumps.
+        while let Some(Some(res)) = period.next().now_or_never() {
+            res?;
+        }
         id += 1;
         let path = format!("{}/{:0>6}{}", dir, id, HEAP_PROFILE_SUFFIX);
-        dump_prof(&path).map_err(|e| format!("dump_prof: {}", e))?;
-        info!("a heap profile is dumped to {}", path);
+        // Keep the dump off the async executor; it can be slow on large heaps.
+        spawn_blocking_io(move || dump_prof(&path).map_err(|e| format!("dump_prof: {}", e)))
+            .await?;
+        info!("a heap profile is dumped with id {:0>6}", id);
     }
     Ok(())
 }
 
+// Build a CPU profiler guard with the shared frequency and blocklist. Errors
+// if another guard is already active process-wide — pprof-rs backs this with
+// a single global profiler, so this is also how a second concurrent CPU
+// session gets rejected.
+fn build_cpu_profiler_guard(frequency: i32) -> Result<pprof::ProfilerGuard<'static>, String> {
+    pprof::ProfilerGuardBuilder::default()
+        .frequency(frequency)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|e| format!("pprof::ProfilerGuardBuilder::build fail: {}", e))
+}
+
+// Build a report from a CPU profiler guard, normalizing thread names.
+fn build_cpu_report(guard: &pprof::ProfilerGuard<'static>) -> Result<pprof::Report, String> {
+    guard
+        .report()
+        .frames_post_processor(move |frames| {
+            let name = extract_thread_name(&frames.thread_name);
+            frames.thread_name = name;
+        })
+        .build()
+        .map_err(|e| format!("create cpu profiling report fail: {}", e))
+}
+
+// Serialize a CPU report into a gzip-wrapped pprof profile.
+fn report_to_pprof(report: &pprof::Report, frequency: i32) -> Result<Vec<u8>, String> {
+    let mut profile = report
+        .pprof()
+        .map_err(|e| format!("generate pprof from report fail: {}", e))?;
+    fill_cpu_profile_metadata(&mut profile, frequency);
+    let mut encoded = Vec::new();
+    profile
+        .write_to_vec(&mut encoded)
+        .map_err(|e| format!("encode pprof into bytes fail: {}", e))?;
+    gzip_encode(&encoded)
+}
+
+// Serialize a CPU report into the folded/collapsed stack format: one
+// `thread;frame1;frame2;...;frameN count` line per unique thread and call
+// stack, with the thread name as the root and the frames ordered root-first. This is trivial to derive from the report's per-stack sample
+// counts and keeps the raw data scriptable for CI regression gates.
+fn report_to_collapsed(report: &pprof::Report) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    for (key, count) in report.data.iter() {
+        let mut line = key.thread_name_or_id();
+        for frame in key.frames.iter().rev() {
+            for symbol in frame.iter().rev() {
+                line.push(';');
+                line.push_str(&format!("{}", symbol));
+            }
+        }
+        writeln!(body, "{} {}", line, count)
+            .map_err(|e| format!("write collapsed stack fail: {}", e))?;
+    }
+    Ok(body)
+}
+
+/// Gzip-compress a raw payload. Standard pprof tooling (`go tool pprof`,
+/// Pyroscope, Grafana) expects the protobuf body to be gzip-wrapped.
+fn gzip_encode(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 2), Compression::default());
+    encoder
+        .write_all(bytes)
+        .map_err(|e| format!("gzip encode fail: {}", e))?;
+    encoder
+        .finish()
+        .map_err(|e| format!("gzip finish fail: {}", e))
+}
+
+// Intern a string into a pprof string table, returning its index.
+fn intern_string(table: &mut Vec<String>, s: &str) -> i64 {
+    if let Some(idx) = table.iter().position(|x| x == s) {
+        return idx as i64;
+    }
+    table.push(s.to_owned());
+    (table.len() - 1) as i64
+}
+
+// Populate the `sample_type`, `period` and `period_type` fields that pprof
+// consumers rely on. `period` is the sampling interval in nanoseconds derived
+// from the configured sampling `frequency` (in Hz).
+fn fill_cpu_profile_metadata(profile: &mut pprof::protos::Profile, frequency: i32) {
+    let cpu = intern_string(&mut profile.string_table, "cpu");
+    let nanoseconds = intern_string(&mut profile.string_table, "nanoseconds");
+
+    // `report.pprof()` emits exactly one value per sample, so `sample_type`
+    // must be a single `ValueType`; a length mismatch makes `go tool pprof`
+    // reject the profile.
+    if profile.sample_type.is_empty() {
+        let mut cpu_value = pprof::protos::ValueType::new();
+        cpu_value.type_ = cpu;
+        cpu_value.unit = nanoseconds;
+        profile.sample_type = vec![cpu_value];
+    }
+
+    let mut period_type = pprof::protos::ValueType::new();
+    period_type.type_ = cpu;
+    period_type.unit = nanoseconds;
+    profile.period_type = protobuf::MessageField::some(period_type);
+    if frequency > 0 {
+        profile.period = 1_000_000_000 / frequency as i64;
+    }
+}
+
 fn extract_thread_name(thread_name: &str) -> String {
     THREAD_NAME_RE
         .captures(thread_name)
@@ -402,7 +778,11 @@ mod tests {
         assert_eq!(&extract_thread_name("snap_sender1000"), "snap-sender");
     }
 
-    // Test there is at most 1 concurrent profiling.
+    // Heap profiling sessions really do run side by side. CPU profiling only
+    // gets session bookkeeping: pprof-rs's `ProfilerGuard` wraps one
+    // process-global profiler, so a second CPU session started while one is
+    // active is rejected up front instead of corrupting or silently replacing
+    // the first one's guard.
     #[test]
     fn test_profile_guard_concurrency() {
         use std::{thread, time::Duration};
@@ -415,41 +795,54 @@ mod tests {
             .build()
             .unwrap();
 
-        let expected = "Already in CPU Profiling";
-
-        let (tx1, rx1) = oneshot::channel();
+        let (tx1, rx1) = oneshot::channel::<()>();
         let rx1 = rx1.map_err(|_| "channel canceled".to_owned());
-        let res1 = rt.spawn(start_one_cpu_profile(rx1, 99, false));
+        let res1 = rt.spawn(start_one_cpu_profile(rx1, 99, CpuProfileFormat::Flamegraph));
         thread::sleep(Duration::from_millis(100));
+        assert_eq!(CPU_PROFILE_REGISTRY.lock().unwrap().sessions.len(), 1);
 
-        let (_tx2, rx2) = oneshot::channel();
+        // A second CPU profile attempted while the first is still sampling
+        // never gets as far as registering a session: it fails in `on_start`
+        // against pprof's own global-profiler guard, so the first session's
+        // bookkeeping is untouched.
+        let (tx2, rx2) = oneshot::channel::<()>();
         let rx2 = rx2.map_err(|_| "channel canceled".to_owned());
-        let res2 = rt.spawn(start_one_cpu_profile(rx2, 99, false));
-        assert_eq!(block_on(res2).unwrap().unwrap_err(), expected);
+        let res2 = rt.spawn(start_one_cpu_profile(rx2, 49, CpuProfileFormat::Protobuf));
+        block_on(res2).unwrap().unwrap_err();
+        drop(tx2);
+        assert_eq!(CPU_PROFILE_REGISTRY.lock().unwrap().sessions.len(), 1);
 
+        // The first session is unaffected by the rejected second one; ending
+        // it (by canceling, same as before) clears its own bookkeeping entry.
         drop(tx1);
         block_on(res1).unwrap().unwrap_err();
+        assert_eq!(CPU_PROFILE_REGISTRY.lock().unwrap().sessions.len(), 0);
 
-        let expected = "Already in Heap Profiling";
-
+        // Two heap profiling sessions coexist and are handed distinct ids.
+        let (id_tx1, id_rx1) = sync_channel::<SessionId>(1);
         let (tx1, rx1) = mpsc::channel(1);
         let res1 = rt.spawn(activate_heap_profile(
             Some(rx1),
             std::env::temp_dir(),
-            || {},
+            move |id| id_tx1.send(id).unwrap(),
         ));
-        thread::sleep(Duration::from_millis(100));
 
-        let (_tx2, rx2) = mpsc::channel(1);
+        let (id_tx2, id_rx2) = sync_channel::<SessionId>(1);
+        let (tx2, rx2) = mpsc::channel(1);
         let res2 = rt.spawn(activate_heap_profile(
             Some(rx2),
             std::env::temp_dir(),
-            || {},
+            move |id| id_tx2.send(id).unwrap(),
         ));
-        assert_eq!(block_on(res2).unwrap().unwrap_err(), expected);
+
+        let id1 = id_rx1.recv().unwrap();
+        let id2 = id_rx2.recv().unwrap();
+        assert_ne!(id1, id2);
 
         drop(tx1);
+        drop(tx2);
         block_on(res1).unwrap().unwrap();
+        block_on(res2).unwrap().unwrap();
     }
 
     #[test]
@@ -462,14 +855,13 @@ mod tests {
 
         // Test activated profiling can be stopped by canceling the period stream.
         let (tx, rx) = mpsc::channel(1);
-        let res = rt.spawn(activate_heap_profile(Some(rx), std::env::temp_dir(), || {}));
+        let res = rt.spawn(activate_heap_profile(Some(rx), std::env::temp_dir(), |_| {}));
         drop(tx);
         block_on(res).unwrap().unwrap();
 
-        // Test activated profiling can be stopped by the handle.
-        let (tx, rx) = sync_channel::<i32>(1);
-        let on_activated = move || drop(tx);
-        let check_activated = move || rx.recv().is_err();
+        // Test activated profiling can be stopped by its handle.
+        let (tx, rx) = sync_channel::<SessionId>(1);
+        let on_activated = move |id| tx.send(id).unwrap();
 
         let (_tx, _rx) = mpsc::channel(1);
         let res = rt.spawn(activate_heap_profile(
@@ -477,8 +869,8 @@ mod tests {
             std::env::temp_dir(),
             on_activated,
         ));
-        assert!(check_activated());
-        assert!(deactivate_heap_profile());
+        let id = rx.recv().unwrap();
+        assert!(deactivate_heap_profile(id));
         block_on(res).unwrap().unwrap();
     }
 
@@ -492,14 +884,13 @@ mod tests {
 
         // Test heap profiling can be stopped by sending an error.
         let (mut tx, rx) = mpsc::channel(1);
-        let res = rt.spawn(activate_heap_profile(Some(rx), std::env::temp_dir(), || {}));
+        let res = rt.spawn(activate_heap_profile(Some(rx), std::env::temp_dir(), |_| {}));
         block_on(tx.send(Err("test".to_string()))).unwrap();
         block_on(res).unwrap().unwrap_err();
 
         // Test heap profiling can be activated again.
-        let (tx, rx) = sync_channel::<i32>(1);
-        let on_activated = move || drop(tx);
-        let check_activated = move || rx.recv().is_err();
+        let (tx, rx) = sync_channel::<SessionId>(1);
+        let on_activated = move |id| tx.send(id).unwrap();
 
         let (_tx, _rx) = mpsc::channel(1);
         let res = rt.spawn(activate_heap_profile(
@@ -507,8 +898,8 @@ mod tests {
             std::env::temp_dir(),
             on_activated,
         ));
-        assert!(check_activated());
-        assert!(deactivate_heap_profile());
+        let id = rx.recv().unwrap();
+        assert!(deactivate_heap_profile(id));
         block_on(res).unwrap().unwrap();
     }
 }